@@ -0,0 +1,75 @@
+// The ecs cost of a frame with the board on it, for boards from the default size up to 201x201.
+// there is no renderer, so a frame only propagates the transforms, nothing is extracted or drawn
+// and the draw cost is not in it. the board is the two sprites of setup_boundary, the "per_cell" runs keep
+// one sprite per cell like the ui grid it replaced.
+//
+//     cargo bench --bench board
+
+#![allow(clippy::needless_return)]
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use snake::constants::SNAKE_NODE_SIZE;
+use snake::data::occupancy::BoardOccupancy;
+use snake::data::{Board, Portals};
+use snake::game::setup_boundary;
+use snake::settings::Settings;
+use snake::theme::Theme;
+
+const BOARD_SIZES: [i32; 4] = [17, 51, 101, 201];
+
+// a headless app without a renderer, its frames propagate the transforms of the board like the game does.
+fn board_app(size: i32) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), TransformPlugin, HierarchyPlugin))
+        .init_asset::<Image>()
+        .insert_resource(Settings {
+            board_size: size,
+            ..default()
+        })
+        .init_resource::<Theme>()
+        .init_resource::<Board>()
+        .init_resource::<Portals>()
+        .init_resource::<BoardOccupancy>();
+    return app;
+}
+
+fn spawn_cell_sprites(mut commands: Commands, settings: Res<Settings>, theme: Res<Theme>) {
+    let max = settings.board_size / 2;
+    commands.spawn(SpatialBundle::default()).with_children(|parent| {
+        for x in -max..=max {
+            for y in -max..=max {
+                let color = if (x + y) % 2 == 0 { theme.grid_even } else { theme.grid_odd };
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(SNAKE_NODE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x as f32 * SNAKE_NODE_SIZE, y as f32 * SNAKE_NODE_SIZE, 0.),
+                    ..default()
+                });
+            }
+        }
+    });
+}
+
+fn board_ecs_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("board_ecs_update");
+    for size in BOARD_SIZES {
+        let mut app = board_app(size);
+        app.add_systems(Startup, setup_boundary);
+        app.update();
+        group.bench_with_input(BenchmarkId::new("tiled", size), &size, |b, _| b.iter(|| app.update()));
+
+        let mut app = board_app(size);
+        app.add_systems(Startup, spawn_cell_sprites);
+        app.update();
+        group.bench_with_input(BenchmarkId::new("per_cell", size), &size, |b, _| b.iter(|| app.update()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, board_ecs_update);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+// The cost of a step of the snake for snakes from 100 up to 10000 segments, it should not grow with the length.
+// the board is a long wrapping strip, the straight snake never runs into itself or a wall.
+//
+//     cargo bench --bench snake
+
+#![allow(clippy::needless_return)]
+
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use snake::data::component::{SnakeLength, SnakeNode};
+use snake::data::occupancy::{BoardOccupancy, PLAYER_ONE};
+use snake::data::snake_body::SnakeBody;
+use snake::data::states::InGameState;
+use snake::data::{Board, Direction, GameOverEvent, HazardHitEvent, Portals, RoundMode, SnakeType};
+use snake::update::snake_move;
+
+const SNAKE_LENGTHS: [usize; 3] = [100, 1_000, 10_000];
+
+// a headless app with a straight snake looking to the right, every frame moves it by a cell.
+fn snake_app(length: usize) -> App {
+    let board = Board {
+        columns: 20_001,
+        rows: 3,
+        wrap: true,
+    };
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_state::<InGameState>()
+        .add_event::<GameOverEvent>()
+        .add_event::<HazardHitEvent>()
+        .insert_resource(board)
+        .init_resource::<Portals>()
+        .init_resource::<RoundMode>();
+
+    let mut snake_body = SnakeBody::default();
+    let mut occupancy = BoardOccupancy::new(board);
+    // grown from the tail to the head.
+    for i in 0..length {
+        let cell = IVec2::new(i as i32 - length as i32 / 2, 0);
+        let snake_type = if i + 1 == length { SnakeType::Head(Direction::Right) } else { SnakeType::Body };
+        let position = Board::cell_position(cell);
+        let entity = app
+            .world
+            .spawn((Transform::from_xyz(position.x, position.y, 0.), SnakeNode { snake_type }))
+            .id();
+        snake_body.grow(entity, cell);
+        occupancy.add_segment(cell, PLAYER_ONE);
+    }
+    app.world.spawn((Text::default(), SnakeLength(length - 1)));
+    app.insert_resource(snake_body)
+        .insert_resource(occupancy)
+        .add_systems(Update, snake_move);
+    app.update();
+    return app;
+}
+
+fn snake_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("snake_step");
+    for length in SNAKE_LENGTHS {
+        let mut app = snake_app(length);
+        group.bench_with_input(BenchmarkId::from_parameter(length), &length, |b, _| b.iter(|| app.update()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, snake_step);
+criterion_main!(benches);
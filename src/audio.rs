@@ -0,0 +1,148 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+
+use crate::constants::NORMAL_MODE_FIXED_TIMESTEP;
+use crate::data::component::MenuButtonAction;
+use crate::data::states::{AppState, InGameState};
+use crate::data::SoundEffect;
+use crate::focus::FocusedButton;
+use crate::input_map::ActionInput;
+use crate::settings::Settings;
+
+// the music is quieter than the effects.
+const MUSIC_VOLUME: f32 = 0.5;
+// how far the tempo of the music follows the speed of the snake.
+const MUSIC_MIN_SPEED: f32 = 0.8;
+const MUSIC_MAX_SPEED: f32 = 1.5;
+
+// The sound effects and the background music, only built with the audio feature.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Sounds>()
+            .add_systems(Startup, (load_sounds_system, start_music_system).chain())
+            .add_systems(OnEnter(InGameState::Dying), play_die_sound_system)
+            .add_systems(OnEnter(InGameState::Paused), play_pause_sound_system)
+            .add_systems(Update, (play_sound_effects_system, menu_click_sound_system, music_system));
+    }
+}
+
+#[derive(Resource, Default)]
+struct Sounds {
+    eat: Handle<AudioSource>,
+    turn: Handle<AudioSource>,
+    die: Handle<AudioSource>,
+    pause: Handle<AudioSource>,
+    click: Handle<AudioSource>,
+    music: Handle<AudioSource>,
+}
+
+// Marks the entity playing the background music.
+#[derive(Component)]
+struct Music;
+
+fn load_sounds_system(asset_server: Res<AssetServer>, mut sounds: ResMut<Sounds>) {
+    *sounds = Sounds {
+        eat: asset_server.load("sounds/eat.wav"),
+        turn: asset_server.load("sounds/turn.wav"),
+        die: asset_server.load("sounds/die.wav"),
+        pause: asset_server.load("sounds/pause.wav"),
+        click: asset_server.load("sounds/click.wav"),
+        music: asset_server.load("sounds/music.wav"),
+    };
+}
+
+fn start_music_system(mut commands: Commands, sounds: Res<Sounds>, settings: Res<Settings>) {
+    commands.spawn((
+        AudioBundle {
+            source: sounds.music.clone(),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(music_volume(&settings))),
+        },
+        Music,
+    ));
+}
+
+fn effect_volume(settings: &Settings) -> f32 {
+    return if settings.muted { 0. } else { settings.volume };
+}
+
+fn music_volume(settings: &Settings) -> f32 {
+    return effect_volume(settings) * MUSIC_VOLUME;
+}
+
+// plays the sound once, the entity is removed when it ends.
+fn play(commands: &mut Commands, settings: &Settings, source: &Handle<AudioSource>) {
+    let volume = effect_volume(settings);
+    if volume <= 0. {
+        return;
+    }
+    commands.spawn(AudioBundle {
+        source: source.clone(),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+    });
+}
+
+fn play_sound_effects_system(
+    mut commands: Commands,
+    mut events: EventReader<SoundEffect>,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+) {
+    for effect in events.read() {
+        let source = match effect {
+            SoundEffect::Eat => &sounds.eat,
+            SoundEffect::Turn => &sounds.turn,
+        };
+        play(&mut commands, &settings, source);
+    }
+}
+
+fn play_die_sound_system(mut commands: Commands, sounds: Res<Sounds>, settings: Res<Settings>) {
+    play(&mut commands, &settings, &sounds.die);
+}
+
+fn play_pause_sound_system(mut commands: Commands, sounds: Res<Sounds>, settings: Res<Settings>) {
+    play(&mut commands, &settings, &sounds.pause);
+}
+
+// clicks when a menu button is pressed by the mouse, a touch or Enter, the hotkeys stay silent.
+fn menu_click_sound_system(
+    mut commands: Commands,
+    sounds: Res<Sounds>,
+    settings: Res<Settings>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<MenuButtonAction>)>,
+    focused_button: FocusedButton,
+    action_input: ActionInput,
+) {
+    let pressed = interaction_query.iter().any(|interaction| *interaction == Interaction::Pressed);
+    if pressed || (action_input.menu_confirm() && focused_button.action().is_some()) {
+        play(&mut commands, &settings, &sounds.click);
+    }
+}
+
+// follows the volume settings, and the speed of the snake while playing.
+fn music_system(
+    settings: Res<Settings>,
+    fixed_time: Res<Time<Fixed>>,
+    app_state: Res<State<AppState>>,
+    music_query: Query<&AudioSink, With<Music>>,
+) {
+    let Ok(sink) = music_query.get_single() else {
+        return;
+    };
+    let volume = music_volume(&settings);
+    if sink.volume() != volume {
+        sink.set_volume(volume);
+    }
+    let speed = match app_state.get() {
+        AppState::InGame => {
+            let ratio = NORMAL_MODE_FIXED_TIMESTEP / fixed_time.timestep().as_secs_f64();
+            (ratio as f32).clamp(MUSIC_MIN_SPEED, MUSIC_MAX_SPEED)
+        }
+        _ => 1.,
+    };
+    if sink.speed() != speed {
+        sink.set_speed(speed);
+    }
+}
@@ -2,10 +2,67 @@ pub const SNAKE_NODE_SIZE: f32 = 36.0;
 pub const EASY_MODE_FIXED_TIMESTEP: f64 = 1.0;
 pub const NORMAL_MODE_FIXED_TIMESTEP: f64 = 0.6;
 pub const HARD_MODE_FIXED_TIMESTEP: f64 = 0.3;
+pub const RESUME_COUNTDOWN_SECONDS: u32 = 3;
+pub const START_COUNTDOWN_SECONDS: u32 = 3;
 
-pub const BOUNDARY_WIDTH: f32 = 17. * SNAKE_NODE_SIZE;
-pub const BOUNDARY_HEIGHT: f32 = 17. * SNAKE_NODE_SIZE;
+// the left stick is ignored until it is pushed this far.
+pub const GAMEPAD_STICK_DEADZONE: f32 = 0.5;
+
+// a touch that moves less than this is a tap, more is a swipe.
+pub const SWIPE_MIN_DISTANCE: f32 = 30.;
+pub const TOUCH_DPAD_BUTTON_SIZE: f32 = 72.;
+
+// the board always has an odd number of cells, so the center cell sits at (0, 0).
+pub const DEFAULT_BOARD_SIZE: i32 = 17;
+pub const BOARD_SIZES: [i32; 4] = [11, 13, 15, 17];
 pub const BOUNDARY_BORDER_WIDTH: f32 = 4.;
+// a turned portal still fits in its cell.
+pub const PORTAL_SIZE: f32 = SNAKE_NODE_SIZE * 0.7;
+// the board is drawn under the snake and the food.
+pub const BOARD_Z: f32 = -10.;
 
 pub const WINDOW_WIDTH: f32 = 1100.;
-pub const WINDOW_HEIGHT: f32 = 800.;
\ No newline at end of file
+pub const WINDOW_HEIGHT: f32 = 800.;
+
+// the smallest area that holds the menus, the ui is scaled to fit it in the window.
+pub const LAYOUT_MIN_WIDTH: f32 = 700.;
+pub const LAYOUT_MIN_HEIGHT: f32 = 800.;
+// the room kept around the board, the score is drawn above it and the same room is kept below.
+pub const BOARD_MARGIN: f32 = 20.;
+pub const SCORE_HEIGHT: f32 = 70.;
+
+// the effects of juice.rs, they can be turned off in the settings.
+pub const PARTICLE_COUNT: usize = 12;
+pub const PARTICLE_SIZE: f32 = 6.;
+pub const PARTICLE_SPEED: f32 = 160.;
+pub const PARTICLE_SECONDS: f32 = 0.5;
+pub const PULSE_SECONDS: f32 = 0.25;
+pub const PULSE_SCALE: f32 = 0.3;
+pub const SHAKE_SECONDS: f32 = 0.35;
+pub const SHAKE_STRENGTH: f32 = 10.;
+pub const SCATTER_SPEED: f32 = 260.;
+
+// the death animation, the body fades from the neck to the tail while the head flashes.
+pub const DYING_MIN_SECONDS: f32 = 1.;
+pub const DYING_BODY_SECONDS: f32 = 1.2; // the longest a long snake takes to start fading its last segment.
+pub const DYING_SEGMENT_SECONDS: f32 = 0.08;
+pub const DYING_FADE_SECONDS: f32 = 0.3;
+pub const DYING_FLASH_SECONDS: f32 = 0.12;
+
+// the food, the food counts to choose from in the settings.
+pub const FOOD_COUNTS: [usize; 4] = [1, 2, 3, 5];
+pub const FOOD_LIFETIME_SECONDS: f32 = 8.;
+pub const FOOD_BLINK_SECONDS: f32 = 2.;
+pub const FOOD_BLINK_INTERVAL: f32 = 0.15;
+pub const FOOD_RESPAWN_SECONDS: f32 = 1.5;
+
+// a survival round gets a new wall this often, never this close to the head.
+pub const SURVIVAL_OBSTACLE_SECONDS: f32 = 8.;
+pub const SURVIVAL_OBSTACLE_DISTANCE: i32 = 3;
+pub const HIGH_SCORE_COUNT: usize = 5;
+
+// the seconds between two steps of a hazard, the hunter is the slowest.
+pub const HAZARD_PATROL_SECONDS: f32 = 0.4;
+pub const HAZARD_BALL_SECONDS: f32 = 0.3;
+pub const HAZARD_HUNTER_SECONDS: f32 = 0.8;
+pub const HAZARD_LOST_SEGMENTS: usize = 3;
@@ -1,32 +1,141 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, IVec2, Timer, Vec2};
 
-use super::SnakeType;
+use super::occupancy::FoodKind;
+use super::{Direction, HazardKind, SnakeType};
+use crate::input_map::InputAction;
+use crate::settings::SettingItem;
 
 
-#[derive(Component)]
-pub struct Snake;
-
 #[derive(Component, Debug, Clone, Copy)]
 pub struct SnakeNode {
     pub snake_type: SnakeType,
 }
 
+// The colored quads of a snake node, they are drawn when there is no snake atlas.
+#[derive(Component)]
+pub struct SnakeQuad;
+
+// The piece of the snake atlas drawn for a snake node.
 #[derive(Component)]
-pub struct Food;
+pub struct SnakePiece;
 
+// Food on the board, food with a lifetime blinks for its last seconds and goes, see food.rs.
 #[derive(Component)]
-pub struct GameOver;
+pub struct Food {
+    pub kind: FoodKind,
+    pub cell: IVec2,
+    pub lifetime: Option<Timer>,
+}
+
+// A hazard moving on the board, it steps to the next cell every time its timer finishes.
+#[derive(Component)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub cell: IVec2,
+    pub timer: Timer,
+}
+
+// A spark of the burst on the eaten food, it flies out and fades.
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub timer: Timer,
+}
+
+// Swells the snake node for a moment, the new head gets it.
+#[derive(Component)]
+pub struct Pulse(pub Timer);
+
+// Throws a snake node off the board when the round is lost.
+#[derive(Component)]
+pub struct Scatter {
+    pub velocity: Vec2,
+    pub spin: f32,
+}
 
 #[derive(Component)]
 pub struct SnakeLength(pub usize);
 
+// The text under the board showing the time left of a time attack.
+#[derive(Component)]
+pub struct ModeText;
+
+// A wall put up in the middle of a survival round.
+#[derive(Component)]
+pub struct Obstacle;
+
+#[derive(Component)]
+pub struct Boundary;
+
 #[derive(Component)]
 pub struct OnMainMenuScreen;
 
 #[derive(Component)]
+pub struct OnGameOverScreen;
+
+#[derive(Component)]
+pub struct OnPauseScreen;
+
+#[derive(Component)]
+pub struct PauseCountdownText;
+
+#[derive(Component)]
+pub struct OnPrepareScreen;
+
+#[derive(Component)]
+pub struct OnSettingsScreen;
+
+#[derive(Component)]
+pub struct OnKeyBindingsScreen;
+
+#[derive(Component)]
+pub struct PrepareText;
+
+#[derive(Component)]
+pub struct TouchDpad;
+
+#[derive(Component)]
+pub struct TouchDpadButton(pub Direction);
+
+#[derive(Component, Clone, Copy)]
 pub enum MenuButtonAction {
     StartGameLevel1,
     StartGameLevel2,
     StartGameLevel3,
+    StartDefaultGame,
+    CycleGameMode,
+    Resume,
+    EndRound,
+    Restart,
+    MainMenu,
+    OpenSettings,
+    CloseSettings,
+    CycleSetting(SettingItem),
+    OpenKeyBindings,
+    CloseKeyBindings,
+    Rebind(InputAction),
+    ResetKeyBindings,
     Quit,
-}
\ No newline at end of file
+}
+
+// The color of the current theme an entity is painted with, see apply_theme_system.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum ThemeColor {
+    Board,
+    BoardBorder,
+    GridEven,
+    GridOdd,
+    SnakeBorder,
+    SnakeBody,
+    Food,
+    Portal,
+    Hazard,
+    Score,
+    Title,
+    GameOverTitle,
+    Text,
+    Panel,
+    ModalPanel,
+    Backdrop,
+    TouchButton,
+}
@@ -1,6 +1,12 @@
-use bevy::prelude::Resource;
+use bevy::prelude::{Entity, Event, IVec2, Resource, Vec2};
+use bevy::time::{Timer, TimerMode};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
 
 pub mod component;
+pub mod occupancy;
+pub mod snake_body;
 pub mod states;
 
 #[derive(Resource)]
@@ -20,6 +26,457 @@ impl PauseStateRes {
     pub fn is_pause_state(&self) -> bool {
         return self.user_click_pause || self.lose_focus_pause;
     }
+
+    pub fn clear(&mut self) {
+        self.user_click_pause = false;
+        self.lose_focus_pause = false;
+    }
+}
+
+// Counts down the seconds left before the snake starts moving,
+// used when a round starts and when a paused round resumes.
+#[derive(Resource, Default)]
+pub struct Countdown {
+    pub remaining: u32,
+    pub timer: Timer,
+}
+
+impl Countdown {
+    pub fn start(&mut self, seconds: u32) {
+        self.remaining = seconds;
+        self.timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    pub fn cancel(&mut self) {
+        self.remaining = 0;
+    }
+
+    pub fn is_running(&self) -> bool {
+        return self.remaining > 0;
+    }
+}
+
+// How a new round leaves InGameState::Preparing.
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum PrepareMode {
+    #[default]
+    Countdown,
+    WaitForKey, // the round starts with the first direction key.
+}
+
+// The kind of round, chosen in the main menu. every mode keeps its own high scores.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Classic, // until the snake crashes.
+    TimeAttack60, // the most points before the time is up.
+    TimeAttack120,
+    Survival, // a new wall comes up on the board every few seconds.
+    Zen,      // the snake can not die, the walls wrap and biting the body cuts the tail off.
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            GameMode::Classic => "Classic",
+            GameMode::TimeAttack60 => "Time Attack 60s",
+            GameMode::TimeAttack120 => "Time Attack 120s",
+            GameMode::Survival => "Survival",
+            GameMode::Zen => "Zen",
+        };
+    }
+
+    pub fn next(&self) -> GameMode {
+        return match self {
+            GameMode::Classic => GameMode::TimeAttack60,
+            GameMode::TimeAttack60 => GameMode::TimeAttack120,
+            GameMode::TimeAttack120 => GameMode::Survival,
+            GameMode::Survival => GameMode::Zen,
+            GameMode::Zen => GameMode::Classic,
+        };
+    }
+
+    // the seconds of a time attack.
+    pub fn time_limit(&self) -> Option<f32> {
+        return match self {
+            GameMode::TimeAttack60 => Some(60.),
+            GameMode::TimeAttack120 => Some(120.),
+            _ => None,
+        };
+    }
+
+    pub fn can_die(&self) -> bool {
+        return *self != GameMode::Zen;
+    }
+}
+
+// What happens when the head runs into the body of the snake.
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum SelfCollisionPolicy {
+    #[default]
+    Die,
+    CutAtImpact, // the bitten segment and the ones after it are cut off, the score drops with them.
+    PassThrough, // the head crosses the body as if it was not there.
+}
+
+impl SelfCollisionPolicy {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            SelfCollisionPolicy::Die => "Die",
+            SelfCollisionPolicy::CutAtImpact => "Cut Tail",
+            SelfCollisionPolicy::PassThrough => "Pass Through",
+        };
+    }
+
+    pub fn next(&self) -> SelfCollisionPolicy {
+        return match self {
+            SelfCollisionPolicy::Die => SelfCollisionPolicy::CutAtImpact,
+            SelfCollisionPolicy::CutAtImpact => SelfCollisionPolicy::PassThrough,
+            SelfCollisionPolicy::PassThrough => SelfCollisionPolicy::Die,
+        };
+    }
+}
+
+// The mode of the running round and its clocks, set up when the round is prepared.
+#[derive(Resource, Default)]
+pub struct RoundMode {
+    pub mode: GameMode,
+    pub self_collision: SelfCollisionPolicy, // from the settings, zen always cuts.
+    pub time_left: f32,        // of a time attack.
+    pub obstacle_timer: Timer, // until the next wall of a survival round.
+}
+
+// The moving hazards put on the board when a round is prepared, see hazard.rs.
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum HazardLevel {
+    #[default]
+    Off,
+    Calm, // a patrolling block and a bouncing ball, touching one costs segments.
+    Wild, // two blocks, the ball and a hunter, touching one ends the round.
+}
+
+impl HazardLevel {
+    pub fn penalty(&self) -> HazardPenalty {
+        return match self {
+            HazardLevel::Wild => HazardPenalty::GameOver,
+            _ => HazardPenalty::LoseSegments(HAZARD_LOST_SEGMENTS),
+        };
+    }
+
+    // the hazards of the level and the cells they start on, none of them starts on the middle cell or a portal.
+    pub fn layout(&self, board: &Board) -> Vec<(HazardKind, IVec2)> {
+        let x = board.max_x() - 1;
+        let y = board.max_y() - 1;
+        let top_patrol = HazardKind::Patrol {
+            path: (-x..=x).map(|column| IVec2::new(column, y)).collect(),
+            index: 0,
+            forward: true,
+        };
+        let ball = HazardKind::Ball { velocity: IVec2::ONE };
+        let mut hazards = match self {
+            HazardLevel::Off => vec![],
+            _ => vec![(top_patrol, IVec2::new(-x, y)), (ball, IVec2::new(-x, -y))],
+        };
+        if *self == HazardLevel::Wild {
+            let side_patrol = HazardKind::Patrol {
+                path: (1 - y..y).map(|row| IVec2::new(x, row)).collect(),
+                index: 0,
+                forward: true,
+            };
+            hazards.push((side_patrol, IVec2::new(x, 1 - y)));
+            hazards.push((HazardKind::Hunter, IVec2::new(-x, y - 1)));
+        }
+        return hazards;
+    }
+}
+
+// How a hazard moves on its own tick.
+#[derive(PartialEq, Debug, Clone)]
+pub enum HazardKind {
+    Patrol { path: Vec<IVec2>, index: usize, forward: bool }, // back and forth along the path.
+    Ball { velocity: IVec2 }, // diagonally, it bounces off everything but the snake.
+    Hunter, // a step on the shortest way to the head of the snake.
+}
+
+// What touching a hazard does to the snake in the running round.
+#[derive(Resource, PartialEq, Debug, Clone, Copy, Default)]
+pub enum HazardPenalty {
+    #[default]
+    GameOver,
+    LoseSegments(usize), // a snake without the segments to lose ends the round.
+}
+
+// Sent when the snake and a hazard touch, by snake_move or by the moving hazard.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct HazardHitEvent;
+
+#[derive(PartialEq, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn fixed_timestep(&self) -> f64 {
+        return match self {
+            Difficulty::Easy => EASY_MODE_FIXED_TIMESTEP,
+            Difficulty::Normal => NORMAL_MODE_FIXED_TIMESTEP,
+            Difficulty::Hard => HARD_MODE_FIXED_TIMESTEP,
+        };
+    }
+}
+
+// The board of the running round, it is taken from the settings when the round is prepared,
+// so changing the settings in the middle of a round does not move the walls.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Board {
+    pub columns: i32,
+    pub rows: i32,
+    pub wrap: bool, // the snake comes out on the other side instead of hitting the wall.
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        return Board {
+            columns: DEFAULT_BOARD_SIZE,
+            rows: DEFAULT_BOARD_SIZE,
+            wrap: false,
+        };
+    }
+}
+
+impl Board {
+    pub fn width(&self) -> f32 {
+        return self.columns as f32 * SNAKE_NODE_SIZE;
+    }
+
+    pub fn height(&self) -> f32 {
+        return self.rows as f32 * SNAKE_NODE_SIZE;
+    }
+
+    // the cells go from -max_x to max_x.
+    pub fn max_x(&self) -> i32 {
+        return self.columns / 2;
+    }
+
+    pub fn max_y(&self) -> i32 {
+        return self.rows / 2;
+    }
+
+    pub fn contains(&self, cell: IVec2) -> bool {
+        return cell.x.abs() <= self.max_x() && cell.y.abs() <= self.max_y();
+    }
+
+    // the index of the cell in a grid of all the cells of the board, row by row from the bottom.
+    pub fn cell_index(&self, cell: IVec2) -> Option<usize> {
+        if !self.contains(cell) {
+            return None;
+        }
+        let column = cell.x + self.max_x();
+        let row = cell.y + self.max_y();
+        return Some((row * self.columns + column) as usize);
+    }
+
+    // the cell at the index of Board::cell_index.
+    pub fn cell_of(&self, index: usize) -> IVec2 {
+        let index = index as i32;
+        return IVec2::new(index % self.columns - self.max_x(), index / self.columns - self.max_y());
+    }
+
+    // the cell the head moves to in the next step, it comes out on the other side of a wrapping board.
+    pub fn next_cell(&self, cell: IVec2, direction: Direction) -> IVec2 {
+        return self.wrapped(cell + direction.step());
+    }
+
+    // the cell on the board a cell just outside of it stands for, only a wrapping board has one.
+    pub fn wrapped(&self, cell: IVec2) -> IVec2 {
+        let mut next = cell;
+        if self.wrap {
+            if next.x > self.max_x() {
+                next.x = -self.max_x();
+            } else if next.x < -self.max_x() {
+                next.x = self.max_x();
+            }
+            if next.y > self.max_y() {
+                next.y = -self.max_y();
+            } else if next.y < -self.max_y() {
+                next.y = self.max_y();
+            }
+        }
+        return next;
+    }
+
+    pub fn cell_position(cell: IVec2) -> Vec2 {
+        return cell.as_vec2() * SNAKE_NODE_SIZE;
+    }
+}
+
+// The pairs of portal cells of the running round, the head going into one comes out of the other
+// keeping its direction. the snake never stays on a portal, so its cells stay portals in BoardOccupancy.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Portals {
+    pub pairs: Vec<(IVec2, IVec2)>,
+}
+
+impl Portals {
+    // two pairs facing each other across the middle, halfway from it to the corners.
+    pub fn across(board: &Board) -> Self {
+        let x = board.max_x() / 2;
+        let y = board.max_y() / 2;
+        return Portals {
+            pairs: vec![
+                (IVec2::new(-x, y), IVec2::new(x, -y)),
+                (IVec2::new(x, y), IVec2::new(-x, -y)),
+            ],
+        };
+    }
+
+    pub fn partner(&self, cell: IVec2) -> Option<IVec2> {
+        for &(a, b) in &self.pairs {
+            if cell == a {
+                return Some(b);
+            }
+            if cell == b {
+                return Some(a);
+            }
+        }
+        return None;
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        return self.pairs.iter().flat_map(|&(a, b)| [a, b]);
+    }
+
+    // the cell the head moves to, the one after the partner when the next cell is a portal.
+    pub fn next_cell(&self, board: &Board, cell: IVec2, direction: Direction) -> IVec2 {
+        let next = board.next_cell(cell, direction);
+        return match self.partner(next) {
+            Some(exit) => board.next_cell(exit, direction),
+            None => next,
+        };
+    }
+}
+
+// What ended the round.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DeathCause {
+    Wall,
+    Body, // the head ran into the snake itself.
+    Hazard,
+    TimeUp,
+    Ended, // the player ended the round from the pause menu.
+}
+
+impl DeathCause {
+    pub fn describe(&self) -> &'static str {
+        return match self {
+            DeathCause::Wall => "The snake hit the wall",
+            DeathCause::Body => "The snake bit its own tail",
+            DeathCause::Hazard => "The snake ran into a hazard",
+            DeathCause::TimeUp => "The time is up",
+            DeathCause::Ended => "The round was ended",
+        };
+    }
+
+    // the round can also end with the snake in one piece.
+    pub fn is_crash(&self) -> bool {
+        return !matches!(self, DeathCause::TimeUp | DeathCause::Ended);
+    }
+}
+
+// Sent by snake_move when the round is lost.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GameOverEvent {
+    pub cause: DeathCause,
+    pub score: usize,
+    pub length: usize,
+}
+
+// The death animation of the crashed snake, set up on entering InGameState::Dying.
+#[derive(Resource, Default)]
+pub struct DeathAnimation {
+    pub elapsed: f32,
+    pub head: Option<Entity>,
+    pub body: Vec<Entity>, // from the neck to the tail.
+    pub segment_seconds: f32,
+}
+
+impl DeathAnimation {
+    pub fn duration(&self) -> f32 {
+        let body = self.body.len() as f32 * self.segment_seconds + DYING_FADE_SECONDS;
+        return body.max(DYING_MIN_SECONDS);
+    }
+
+    // how far the segment has faded at the time, from 0 for untouched to 1 for gone.
+    pub fn fade(&self, index: usize, elapsed: f32) -> f32 {
+        let start = index as f32 * self.segment_seconds;
+        return ((elapsed - start) / DYING_FADE_SECONDS).clamp(0., 1.);
+    }
+}
+
+// Sent by should_eat_food, juice.rs bursts the food and pulses the new head.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FoodEatenEvent {
+    pub position: Vec2,
+    pub head: Entity,
+}
+
+// How much food is on the board and how long it stays, taken from the settings when the round is prepared.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct FoodRules {
+    pub count: usize,
+    pub lifetime: Option<f32>, // the food stays until it is eaten without one.
+}
+
+impl Default for FoodRules {
+    fn default() -> Self {
+        return FoodRules {
+            count: 1,
+            lifetime: None,
+        };
+    }
+}
+
+// The food that went uneaten comes back after a moment somewhere else, one timer for each.
+// the eaten food comes back at once.
+#[derive(Resource, Default)]
+pub struct FoodRespawn(pub Vec<Timer>);
+
+// Shakes the camera for the seconds left, it fades out with them.
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    pub remaining: f32,
+}
+
+impl ScreenShake {
+    pub fn start(&mut self) {
+        self.remaining = SHAKE_SECONDS;
+    }
+
+    pub fn is_running(&self) -> bool {
+        return self.remaining > 0.;
+    }
+}
+
+// A sound the game asks for, it is played by audio.rs when the audio feature is on.
+// dying and pausing are heard on entering their states, so they are not sent.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub enum SoundEffect {
+    Eat,
+    Turn,
+}
+
+// The statistics of the running round, the game over screen shows them.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct RunStats {
+    pub cause: Option<DeathCause>,
+    pub score: usize,
+    pub length: usize,
+    pub food_eaten: usize,
+    pub time_survived: f32, // seconds of playing, the pauses are not counted.
+    pub new_best: bool,
 }
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -30,9 +487,80 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+
+    // one cell to the direction, y goes up.
+    pub fn step(&self) -> IVec2 {
+        return match self {
+            Direction::Up => IVec2::Y,
+            Direction::Down => IVec2::NEG_Y,
+            Direction::Left => IVec2::NEG_X,
+            Direction::Right => IVec2::X,
+        };
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum SnakeType {
     Body,
     Head(Direction),
     Unknown, // this is the snake init state.
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(columns: i32, rows: i32, wrap: bool) -> Board {
+        return Board { columns, rows, wrap };
+    }
+
+    #[test]
+    fn cell_index_goes_row_by_row_from_the_bottom() {
+        let board = board(5, 3, false);
+        assert_eq!(board.cell_index(IVec2::new(-2, -1)), Some(0));
+        assert_eq!(board.cell_index(IVec2::new(2, -1)), Some(4));
+        assert_eq!(board.cell_index(IVec2::new(-2, 0)), Some(5));
+        assert_eq!(board.cell_index(IVec2::new(2, 1)), Some(14));
+        for index in 0..15 {
+            assert_eq!(board.cell_index(board.cell_of(index)), Some(index));
+        }
+    }
+
+    #[test]
+    fn cells_outside_the_board_have_no_index() {
+        let board = board(5, 3, false);
+        assert_eq!(board.cell_index(IVec2::new(3, 0)), None);
+        assert_eq!(board.cell_index(IVec2::new(0, -2)), None);
+    }
+
+    #[test]
+    fn only_a_wrapping_board_wraps() {
+        let wrapping = board(5, 3, true);
+        assert_eq!(wrapping.wrapped(IVec2::new(3, 0)), IVec2::new(-2, 0));
+        assert_eq!(wrapping.wrapped(IVec2::new(-3, 2)), IVec2::new(2, -1));
+        assert_eq!(wrapping.next_cell(IVec2::new(0, -1), Direction::Down), IVec2::new(0, 1));
+        let walled = board(5, 3, false);
+        assert_eq!(walled.next_cell(IVec2::new(2, 0), Direction::Right), IVec2::new(3, 0));
+    }
+
+    #[test]
+    fn the_head_comes_out_after_the_partner_portal() {
+        let board = board(9, 9, false);
+        let portals = Portals::across(&board);
+        assert_eq!(portals.next_cell(&board, IVec2::new(-3, 2), Direction::Right), IVec2::new(3, -2));
+        assert_eq!(portals.next_cell(&board, IVec2::new(2, -3), Direction::Up), IVec2::new(-2, 3));
+        assert_eq!(portals.next_cell(&board, IVec2::new(0, 0), Direction::Up), IVec2::new(0, 1));
+    }
+
+    #[test]
+    fn a_portal_on_the_edge_exits_across_a_wrapping_board() {
+        let board = board(9, 9, true);
+        let portals = Portals {
+            pairs: vec![(IVec2::new(0, 0), IVec2::new(4, 0))],
+        };
+        assert_eq!(portals.next_cell(&board, IVec2::new(-1, 0), Direction::Right), IVec2::new(-4, 0));
+        assert_eq!(portals.next_cell(&board, IVec2::new(-4, 0), Direction::Left), IVec2::new(-1, 0));
+    }
+}
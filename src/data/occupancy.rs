@@ -0,0 +1,193 @@
+use bevy::prelude::{IVec2, Resource};
+use rand::Rng;
+
+use super::Board;
+
+// how often a random cell is tried before the free cells are looked for one by one,
+// only a nearly full board gets that far.
+const RANDOM_CELL_TRIES: usize = 32;
+
+// the player a snake belongs to, there is one snake for now.
+pub const PLAYER_ONE: u8 = 0;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum FoodKind {
+    #[default]
+    Normal,
+}
+
+// What is on a cell of the board.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum CellContent {
+    #[default]
+    Empty,
+    Snake(u8), // a segment of the snake of the player.
+    Food(FoodKind),
+    Wall,
+    Portal,
+    Hazard,
+}
+
+// The content of every cell of the board, row by row like Board::cell_index.
+// the systems moving or spawning something on the board keep it up to date, so a collision or a free
+// cell is found by looking at one cell instead of at every entity.
+#[derive(Resource)]
+pub struct BoardOccupancy {
+    cells: Vec<CellContent>,
+    // the segments of the snake on every cell, more than one when the snake passes through itself.
+    segments: Vec<u16>,
+    board: Board,
+}
+
+impl Default for BoardOccupancy {
+    fn default() -> Self {
+        return BoardOccupancy::new(Board::default());
+    }
+}
+
+impl BoardOccupancy {
+    pub fn new(board: Board) -> Self {
+        return BoardOccupancy {
+            cells: vec![CellContent::Empty; (board.columns * board.rows) as usize],
+            segments: vec![0; (board.columns * board.rows) as usize],
+            board,
+        };
+    }
+
+    // a cell outside the board is a wall, the head of the snake crashes into it.
+    pub fn get(&self, cell: IVec2) -> CellContent {
+        return match self.board.cell_index(cell) {
+            Some(index) => self.cells[index],
+            None => CellContent::Wall,
+        };
+    }
+
+    pub fn is_free(&self, cell: IVec2) -> bool {
+        return self.get(cell) == CellContent::Empty;
+    }
+
+    // the cells outside the board can not be changed. the segments of the snake are put with add_segment.
+    pub fn set(&mut self, cell: IVec2, content: CellContent) {
+        if let Some(index) = self.board.cell_index(cell) {
+            self.cells[index] = content;
+            self.segments[index] = 0;
+        }
+    }
+
+    pub fn clear(&mut self, cell: IVec2) {
+        self.set(cell, CellContent::Empty);
+    }
+
+    pub fn add_segment(&mut self, cell: IVec2, player: u8) {
+        if let Some(index) = self.board.cell_index(cell) {
+            self.cells[index] = CellContent::Snake(player);
+            self.segments[index] = self.segments[index].saturating_add(1);
+        }
+    }
+
+    // the cell is empty again when the last segment on it leaves.
+    pub fn remove_segment(&mut self, cell: IVec2) {
+        if let Some(index) = self.board.cell_index(cell) {
+            self.segments[index] = self.segments[index].saturating_sub(1);
+            if self.segments[index] == 0 {
+                self.cells[index] = CellContent::Empty;
+            }
+        }
+    }
+
+    // a random empty cell, none when the board is full.
+    pub fn random_free_cell(&self) -> Option<IVec2> {
+        let mut rng = rand::thread_rng();
+        for _ in 0..RANDOM_CELL_TRIES {
+            let cell = IVec2::new(
+                rng.gen_range(-self.board.max_x()..=self.board.max_x()),
+                rng.gen_range(-self.board.max_y()..=self.board.max_y()),
+            );
+            if self.is_free(cell) {
+                return Some(cell);
+            }
+        }
+        let free = self.cells.iter().filter(|&&content| content == CellContent::Empty).count();
+        if free == 0 {
+            return None;
+        }
+        let index = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &content)| content == CellContent::Empty)
+            .nth(rng.gen_range(0..free))
+            .map(|(index, _)| index)?;
+        return Some(self.board.cell_of(index));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occupancy() -> BoardOccupancy {
+        return BoardOccupancy::new(Board {
+            columns: 3,
+            rows: 3,
+            wrap: false,
+        });
+    }
+
+    #[test]
+    fn cells_outside_the_board_are_walls() {
+        let mut occupancy = occupancy();
+        assert_eq!(occupancy.get(IVec2::new(2, 0)), CellContent::Wall);
+        occupancy.set(IVec2::new(2, 0), CellContent::Portal);
+        assert_eq!(occupancy.get(IVec2::new(2, 0)), CellContent::Wall);
+    }
+
+    #[test]
+    fn a_cell_is_free_when_its_last_segment_leaves() {
+        let mut occupancy = occupancy();
+        occupancy.add_segment(IVec2::ZERO, PLAYER_ONE);
+        occupancy.add_segment(IVec2::ZERO, PLAYER_ONE);
+        occupancy.remove_segment(IVec2::ZERO);
+        assert_eq!(occupancy.get(IVec2::ZERO), CellContent::Snake(PLAYER_ONE));
+        occupancy.remove_segment(IVec2::ZERO);
+        assert!(occupancy.is_free(IVec2::ZERO));
+    }
+
+    #[test]
+    fn setting_a_cell_drops_its_segments() {
+        let mut occupancy = occupancy();
+        occupancy.add_segment(IVec2::ZERO, PLAYER_ONE);
+        occupancy.set(IVec2::ZERO, CellContent::Wall);
+        occupancy.add_segment(IVec2::ZERO, PLAYER_ONE);
+        occupancy.remove_segment(IVec2::ZERO);
+        assert!(occupancy.is_free(IVec2::ZERO));
+    }
+
+    #[test]
+    fn a_full_board_has_no_free_cell() {
+        let mut occupancy = occupancy();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                occupancy.set(IVec2::new(x, y), CellContent::Wall);
+            }
+        }
+        assert_eq!(occupancy.random_free_cell(), None);
+    }
+
+    #[test]
+    fn the_last_free_cell_is_found() {
+        let mut occupancy = BoardOccupancy::new(Board {
+            columns: 41,
+            rows: 41,
+            wrap: false,
+        });
+        for x in -20..=20 {
+            for y in -20..=20 {
+                occupancy.set(IVec2::new(x, y), CellContent::Wall);
+            }
+        }
+        // the random tries all but surely miss it, the cells are then looked at one by one.
+        occupancy.clear(IVec2::new(7, -3));
+        assert_eq!(occupancy.random_free_cell(), Some(IVec2::new(7, -3)));
+    }
+}
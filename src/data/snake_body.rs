@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::{Entity, IVec2, Resource};
+
+// The cells of the snake from the head to the tail, the snake nodes only follow it.
+// a step takes the tail off and puts it in front of the head, so it does not depend on the length
+// of the snake. what is on the cells is kept in BoardOccupancy.
+#[derive(Resource, Default)]
+pub struct SnakeBody {
+    segments: VecDeque<(Entity, IVec2)>,
+}
+
+impl SnakeBody {
+    pub fn len(&self) -> usize {
+        return self.segments.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.segments.is_empty();
+    }
+
+    pub fn head(&self) -> Option<(Entity, IVec2)> {
+        return self.segments.front().copied();
+    }
+
+    // the segment at the index counted from the head.
+    pub fn get(&self, index: usize) -> Option<(Entity, IVec2)> {
+        return self.segments.get(index).copied();
+    }
+
+    // from the head to the tail.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, IVec2)> + '_ {
+        return self.segments.iter().copied();
+    }
+
+    // adds a new head, the snake gets longer.
+    pub fn grow(&mut self, entity: Entity, cell: IVec2) {
+        self.segments.push_front((entity, cell));
+    }
+
+    // takes the segments from the index to the tail off, the head is never taken.
+    pub fn split_off(&mut self, index: usize) -> Vec<(Entity, IVec2)> {
+        let index = index.max(1).min(self.segments.len());
+        return Vec::from(self.segments.split_off(index));
+    }
+
+    // moves the tail in front of the head, it returns the entity of the tail, now the head,
+    // and the cell the tail left.
+    pub fn step(&mut self, cell: IVec2) -> (Entity, IVec2) {
+        let (entity, tail) = self.segments.pop_back().expect("the snake has a head");
+        self.segments.push_front((entity, cell));
+        return (entity, tail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a straight snake from (0, 0) to the left, the head first.
+    fn snake(length: u32) -> SnakeBody {
+        let mut body = SnakeBody::default();
+        for i in (0..length).rev() {
+            body.grow(Entity::from_raw(i), IVec2::new(-(i as i32), 0));
+        }
+        return body;
+    }
+
+    #[test]
+    fn a_step_moves_the_tail_in_front_of_the_head() {
+        let mut body = snake(3);
+        let (moved, left) = body.step(IVec2::new(1, 0));
+        assert_eq!(moved, Entity::from_raw(2));
+        assert_eq!(left, IVec2::new(-2, 0));
+        let cells: Vec<IVec2> = body.iter().map(|(_, cell)| cell).collect();
+        assert_eq!(cells, [IVec2::new(1, 0), IVec2::new(0, 0), IVec2::new(-1, 0)]);
+        assert_eq!(body.head(), Some((Entity::from_raw(2), IVec2::new(1, 0))));
+    }
+
+    #[test]
+    fn split_off_takes_the_tail() {
+        let mut body = snake(4);
+        let taken = body.split_off(2);
+        assert_eq!(taken, [(Entity::from_raw(2), IVec2::new(-2, 0)), (Entity::from_raw(3), IVec2::new(-3, 0))]);
+        assert_eq!(body.len(), 2);
+    }
+
+    #[test]
+    fn split_off_keeps_the_head() {
+        let mut body = snake(3);
+        assert_eq!(body.split_off(0).len(), 2);
+        assert_eq!(body.head(), Some((Entity::from_raw(0), IVec2::ZERO)));
+        assert!(body.split_off(5).is_empty());
+        assert_eq!(body.len(), 1);
+        assert!(SnakeBody::default().split_off(0).is_empty());
+    }
+}
@@ -10,7 +10,20 @@ pub enum AppState {
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, States, Default)]
 pub enum InGameState {
     #[default]
+    Idle, // no round is running, e.g. the main menu is showing.
     Preparing,
     Playing,
+    Paused,
+    Dying, // the snake crashed, the death animation plays before the game over menu.
     GameOver,
-}
\ No newline at end of file
+}
+
+// The settings screen is an overlay, so it can be opened from the main menu and from the pause menu
+// without leaving the current AppState.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, States, Default)]
+pub enum SettingsState {
+    #[default]
+    Closed,
+    Open,
+    KeyBindings,
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::data::component::{SnakeNode, ThemeColor};
+use crate::data::snake_body::SnakeBody;
+use crate::data::states::InGameState;
+use crate::data::DeathAnimation;
+use crate::juice::scatter;
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+// the crashed head is drawn over the segment it bit.
+const DYING_HEAD_Z: f32 = 1.;
+
+pub fn enter_dying_system(
+    mut animation: ResMut<DeathAnimation>,
+    snake_body: Res<SnakeBody>,
+    mut node_query: Query<&mut Transform, With<SnakeNode>>,
+) {
+    let head = snake_body.head().map(|(entity, _)| entity);
+    if let Some(Ok(mut transform)) = head.map(|head| node_query.get_mut(head)) {
+        transform.translation.z = DYING_HEAD_Z;
+    }
+    let body = Vec::from_iter(snake_body.iter().skip(1).map(|(entity, _)| entity));
+    // a long snake fades faster, so the animation never takes much longer than DYING_BODY_SECONDS.
+    let segment_seconds = DYING_SEGMENT_SECONDS.min(DYING_BODY_SECONDS / body.len().max(1) as f32);
+    *animation = DeathAnimation {
+        elapsed: 0.,
+        head,
+        body,
+        segment_seconds,
+    };
+}
+
+// flashes the head on the cell it crashed into and fades the body from the neck to the tail,
+// then opens the game over menu.
+pub fn dying_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut animation: ResMut<DeathAnimation>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut node_query: Query<(&Transform, &mut Visibility), With<SnakeNode>>,
+    children_query: Query<&Children>,
+    mut sprite_query: Query<(&mut Sprite, &ThemeColor)>,
+    mut atlas_query: Query<&mut TextureAtlasSprite>,
+) {
+    let before = animation.elapsed;
+    animation.elapsed += time.delta_seconds();
+    let elapsed = animation.elapsed;
+    let done = elapsed >= animation.duration();
+
+    if let Some(Ok((_, mut visibility))) = animation.head.map(|head| node_query.get_mut(head)) {
+        let shown = done || ((elapsed / DYING_FLASH_SECONDS) as u32).is_multiple_of(2);
+        let wanted = if shown { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+
+    for (index, &entity) in animation.body.iter().enumerate() {
+        let fade = animation.fade(index, elapsed);
+        if fade <= 0. || animation.fade(index, before) >= 1. {
+            continue;
+        }
+        // the segment starts to fade in this frame.
+        if animation.fade(index, before) <= 0. && settings.effects {
+            if let Ok((transform, _)) = node_query.get(entity) {
+                commands.entity(entity).insert(scatter(transform));
+            }
+        }
+        for descendant in children_query.iter_descendants(entity) {
+            if let Ok((mut sprite, slot)) = sprite_query.get_mut(descendant) {
+                sprite.color.set_a(theme.color(*slot).a() * (1. - fade));
+            }
+            if let Ok(mut sprite) = atlas_query.get_mut(descendant) {
+                sprite.color.set_a(1. - fade);
+            }
+        }
+    }
+
+    if done {
+        game_state.set(InGameState::GameOver);
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::data::component::MenuButtonAction;
+use crate::data::states::SettingsState;
+use crate::input_map::ActionInput;
+use crate::interaction::is_action_on_top;
+use crate::theme::Theme;
+
+// The menu button the keyboard or the gamepad points at, Enter or the A button clicks it.
+#[derive(Resource, Default)]
+pub struct MenuFocus(pub Option<Entity>);
+
+#[derive(SystemParam)]
+pub struct FocusedButton<'w, 's> {
+    menu_focus: Res<'w, MenuFocus>,
+    button_query: Query<'w, 's, &'static MenuButtonAction, With<Button>>,
+}
+
+impl FocusedButton<'_, '_> {
+    pub fn action(&self) -> Option<MenuButtonAction> {
+        let entity = self.menu_focus.0?;
+        return self.button_query.get(entity).ok().copied();
+    }
+}
+
+// moves the focus between the buttons of the screen in front with Up/Down, Tab or the D-pad,
+// the buttons are visited from top to bottom. every menu gets it, it only looks for MenuButtonAction.
+pub fn menu_focus_system(
+    action_input: ActionInput,
+    settings_state: Res<State<SettingsState>>,
+    button_query: Query<(Entity, &MenuButtonAction, &GlobalTransform, Ref<Interaction>), With<Button>>,
+    mut menu_focus: ResMut<MenuFocus>,
+) {
+    let mut buttons: Vec<(Entity, Vec3)> = button_query
+        .iter()
+        .filter(|(_, action, _, _)| is_action_on_top(*settings_state.get(), **action))
+        .map(|(entity, _, transform, _)| (entity, transform.translation()))
+        .collect();
+    // the y axis of the ui points down.
+    buttons.sort_by(|a, b| a.1.y.total_cmp(&b.1.y).then(a.1.x.total_cmp(&b.1.x)));
+
+    let current = menu_focus
+        .0
+        .and_then(|focused| buttons.iter().position(|(entity, _)| *entity == focused));
+    if current.is_none() && menu_focus.0.is_some() {
+        // the focused button went away with its screen.
+        menu_focus.0 = None;
+    }
+    if buttons.is_empty() {
+        return;
+    }
+
+    // the mouse takes the focus along, the keys go on from the button under it.
+    for (entity, action, _, interaction) in &button_query {
+        if interaction.is_changed()
+            && *interaction == Interaction::Hovered
+            && is_action_on_top(*settings_state.get(), *action)
+            && menu_focus.0 != Some(entity)
+        {
+            menu_focus.0 = Some(entity);
+        }
+    }
+
+    let step = action_input.menu_step();
+    if step == 0 {
+        return;
+    }
+    let next = match current {
+        Some(index) => (index as i32 + step).rem_euclid(buttons.len() as i32) as usize,
+        None if step > 0 => 0,
+        None => buttons.len() - 1,
+    };
+    menu_focus.0 = Some(buttons[next].0);
+}
+
+// colors every menu button by its state, a pressed button wins over the hovered and the focused ones.
+pub fn focus_highlight_system(
+    menu_focus: Res<MenuFocus>,
+    theme: Res<Theme>,
+    mut button_query: Query<(Entity, &Interaction, &mut BackgroundColor), With<MenuButtonAction>>,
+) {
+    for (entity, interaction, mut background_color) in &mut button_query {
+        let color = match interaction {
+            Interaction::Pressed => theme.button_pressed,
+            _ if menu_focus.0 == Some(entity) => theme.button_focused,
+            Interaction::Hovered => theme.button_hovered,
+            Interaction::None => theme.button,
+        };
+        // only write on a change, the ui is drawn again for every changed color.
+        if background_color.0 != color {
+            background_color.0 = color;
+        }
+    }
+}
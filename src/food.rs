@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::data::component::Food;
+use crate::data::occupancy::BoardOccupancy;
+use crate::data::{FoodRespawn, FoodRules};
+use crate::game::spawn_food;
+use crate::theme::Theme;
+
+// counts down the lifetime of the food, it blinks for its last seconds and then goes.
+pub fn food_expiry_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut respawn: ResMut<FoodRespawn>,
+    mut query: Query<(Entity, &mut Food, &mut Visibility)>,
+) {
+    for (entity, mut food, mut visibility) in &mut query {
+        let cell = food.cell;
+        let Some(lifetime) = food.lifetime.as_mut() else {
+            continue;
+        };
+        if lifetime.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn_recursive();
+            occupancy.clear(cell);
+            respawn.0.push(Timer::from_seconds(FOOD_RESPAWN_SECONDS, TimerMode::Once));
+            continue;
+        }
+        let remaining = lifetime.remaining_secs();
+        let shown = remaining > FOOD_BLINK_SECONDS || ((remaining / FOOD_BLINK_INTERVAL) as u32).is_multiple_of(2);
+        let wanted = if shown { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+// puts the food that went uneaten back when its timer finishes.
+pub fn food_respawn_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    rules: Res<FoodRules>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut respawn: ResMut<FoodRespawn>,
+) {
+    let mut finished = 0;
+    respawn.0.retain_mut(|timer| {
+        if timer.tick(time.delta()).finished() {
+            finished += 1;
+            return false;
+        }
+        return true;
+    });
+    for _ in 0..finished {
+        spawn_food(&mut commands, &mut occupancy, &theme, &rules);
+    }
+}
@@ -1,78 +1,152 @@
 use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
 use bevy::sprite::Anchor;
-use bevy::window::Window;
-use rand::Rng;
 
-use crate::constants::{BOUNDARY_HEIGHT, BOUNDARY_WIDTH, SNAKE_NODE_SIZE, BOUNDARY_BORDER_WIDTH};
-use crate::data::component::{Food, SnakeLength, SnakeNode};
-use crate::data::Direction;
-use crate::data::{PauseStateRes, SnakeType};
+use crate::constants::{BOARD_Z, BOUNDARY_BORDER_WIDTH, FOOD_LIFETIME_SECONDS, PORTAL_SIZE, SNAKE_NODE_SIZE};
+use crate::data::component::{Boundary, Food, SnakeLength, SnakeNode, SnakePiece, SnakeQuad, ThemeColor};
+use crate::data::occupancy::{BoardOccupancy, CellContent, FoodKind, PLAYER_ONE};
+use crate::data::snake_body::SnakeBody;
+use crate::data::states::InGameState;
+use crate::data::{Board, FoodRespawn, FoodRules, PauseStateRes, Portals, SnakeType};
+use crate::settings::Settings;
+use crate::snake_sprite::{create_snake_piece_bundle, SnakeAtlas};
+use crate::theme::Theme;
 
 pub fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
 
-pub fn setup_boundary(mut commands: Commands, windows: Query<&mut Window>) {
-    let window = windows.single();
-    let window_width = window.resolution.width();
-    let window_height = window.resolution.height();
-
-    let bg_color = Color::rgba(0., 0.5, 0.25, 0.12);
-    let width = BOUNDARY_WIDTH;
-    let height: f32 = BOUNDARY_HEIGHT;
-    let top_margin = (window_height - height) / 2.0;
-    let left_margin = (window_width - width) / 2.0;
-    commands.spawn(NodeBundle {
-        z_index: ZIndex::Global(-1),
-        style: Style {
-            width: Val::Px(width),
-            height: Val::Px(height),
-            border: UiRect::all(Val::Px(BOUNDARY_BORDER_WIDTH)),
-            margin: UiRect {
-                left: Val::Px(left_margin),
-                top: Val::Px(top_margin),
-                ..Default::default()
-            },
-            // align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            ..Default::default()
-        },
-        background_color: bevy::prelude::BackgroundColor(bg_color),
-        border_color: Color::WHITE.with_a(0.5).into(),
-        ..Default::default()
-    }).with_children(|parent| {
-        let width_count = (BOUNDARY_WIDTH / SNAKE_NODE_SIZE) as i32;
-        let height_count = (BOUNDARY_HEIGHT / SNAKE_NODE_SIZE) as i32;
-        for x in 0..width_count {
-            for y in 0..height_count {
-                let bg_color = if (x + y) % 2  == 0 {
-                    Color::rgba(0.1, 0.24, 0.55, 0.3)
-                } else {
-                    Color::rgba(0.2, 0.14, 0.95, 0.05)
-                };
-                parent.spawn(create_grid_node(x, y, bg_color));
+pub fn setup_boundary(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut board: ResMut<Board>,
+    mut portals: ResMut<Portals>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    *board = Board {
+        columns: settings.board_size,
+        rows: settings.board_size,
+        // a round that can not be lost has no walls to crash into.
+        wrap: settings.wrap || !settings.game_mode.can_die(),
+    };
+    *portals = if settings.portals { Portals::across(&board) } else { Portals::default() };
+    // the snake, the food and the hazards are put on the board after it.
+    *occupancy = BoardOccupancy::new(*board);
+    for cell in portals.cells() {
+        occupancy.set(cell, CellContent::Portal);
+    }
+
+    // the board is drawn in the world like the snake, so the cells line up with it at every window size.
+    commands
+        .spawn((SpatialBundle::from_transform(Transform::from_xyz(0., 0., BOARD_Z)), Boundary))
+        .with_children(|parent| {
+            parent.spawn(create_board_sprite(
+                Vec2::ZERO,
+                Vec2::new(board.width(), board.height()),
+                0.,
+                ThemeColor::Board,
+                &theme,
+            ));
+            // the checkerboard is two sprites whatever the size of the board, one for the even cells
+            // and one for the odd ones, each tinted with its color of the theme.
+            if settings.show_grid {
+                for (even, slot) in [(true, ThemeColor::GridEven), (false, ThemeColor::GridOdd)] {
+                    let (mut sprite, slot) = create_board_sprite(
+                        Vec2::ZERO,
+                        Vec2::new(board.width(), board.height()),
+                        0.1,
+                        slot,
+                        &theme,
+                    );
+                    sprite.texture = images.add(create_grid_mask(&board, even));
+                    parent.spawn((sprite, slot));
+                }
+            }
+            // the walls are drawn just outside the cells.
+            let half_width = board.width() / 2. + BOUNDARY_BORDER_WIDTH / 2.;
+            let half_height = board.height() / 2. + BOUNDARY_BORDER_WIDTH / 2.;
+            let horizontal = Vec2::new(board.width() + 2. * BOUNDARY_BORDER_WIDTH, BOUNDARY_BORDER_WIDTH);
+            let vertical = Vec2::new(BOUNDARY_BORDER_WIDTH, board.height());
+            for (position, size) in [
+                (Vec2::new(0., half_height), horizontal),
+                (Vec2::new(0., -half_height), horizontal),
+                (Vec2::new(-half_width, 0.), vertical),
+                (Vec2::new(half_width, 0.), vertical),
+            ] {
+                parent.spawn(create_board_sprite(position, size, 0.2, ThemeColor::BoardBorder, &theme));
             }
+            // the two portals of a pair look the same, every pair is turned a bit more than the one before.
+            for (index, &(a, b)) in portals.pairs.iter().enumerate() {
+                for cell in [a, b] {
+                    let (mut sprite, slot) = create_board_sprite(
+                        Board::cell_position(cell),
+                        Vec2::splat(PORTAL_SIZE),
+                        0.3,
+                        ThemeColor::Portal,
+                        &theme,
+                    );
+                    sprite.transform.rotate_z(index as f32 * std::f32::consts::FRAC_PI_4);
+                    parent.spawn((sprite, slot));
+                }
+            }
+        });
+}
+
+// one pixel per cell, opaque for the cells of the parity and clear for the others.
+// it is stretched over the board without filtering, so every pixel covers exactly one cell.
+pub fn create_grid_mask(board: &Board, even: bool) -> Image {
+    let mut data = Vec::with_capacity((board.columns * board.rows * 4) as usize);
+    for row in 0..board.rows {
+        for column in 0..board.columns {
+            // the same parity as the cell (x, y) of the board, the rows of the image go down.
+            let x = column - board.max_x();
+            let y = board.max_y() - row;
+            let pixel = if ((x + y) % 2 == 0) == even { [255; 4] } else { [0; 4] };
+            data.extend_from_slice(&pixel);
         }
-    });
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: board.columns as u32,
+            height: board.rows as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::nearest();
+    return image;
 }
 
-fn create_grid_node(x: i32, y: i32, color: Color) -> NodeBundle {
-    return NodeBundle {
-        z_index: ZIndex::Global(-1),
-        style: Style {
-            width: Val::Px(SNAKE_NODE_SIZE),
-            height: Val::Px(SNAKE_NODE_SIZE),
-            left: Val::Px(x as f32 * SNAKE_NODE_SIZE - BOUNDARY_BORDER_WIDTH),
-            top: Val::Px(y as f32 * SNAKE_NODE_SIZE - BOUNDARY_BORDER_WIDTH),
-            // align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            position_type: PositionType::Absolute,
-            ..Default::default()
+fn create_board_sprite(
+    position: Vec2,
+    size: Vec2,
+    z: f32,
+    slot: ThemeColor,
+    theme: &Theme,
+) -> (SpriteBundle, ThemeColor) {
+    return (
+        SpriteBundle {
+            sprite: Sprite {
+                color: theme.color(slot),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(z)),
+            ..default()
         },
-        background_color: bevy::prelude::BackgroundColor(color),
-        border_color: Color::WHITE.with_a(0.5).into(),
-        ..Default::default()
-    };
+        slot,
+    );
+}
+
+pub fn dismiss_boundary(mut commands: Commands, query: Query<Entity, With<Boundary>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
 pub fn dismiss_snake_and_food(
@@ -95,30 +169,31 @@ pub fn dismiss_snake_and_food(
 pub fn setup_snake_score(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    windows: Query<&Window>,
+    board: Res<Board>,
+    theme: Res<Theme>,
 ) {
-    // let window_width = windows.single().resolution.width();
-    let window_height = windows.single().resolution.height();
 
     commands.spawn((
         Text2dBundle {
             text: Text::from_section(
                 "Score: 0",
                 TextStyle {
-                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font: asset_server.load(&theme.font),
                     font_size: 50.0,
-                    color: Color::rgba_u8(119, 195, 200, 184),
+                    color: theme.score,
                 },
             )
             .with_alignment(TextAlignment::Left),
             transform: Transform {
-                translation: Vec3::new(0., (window_height - 110.) / 2., 0.),
+                // just above the board.
+                translation: Vec3::new(0., board.height() / 2. + 35., 0.),
                 ..default()
             },
             text_anchor: Anchor::Center,
             ..default()
         },
         SnakeLength(0),
+        ThemeColor::Score,
     ));
 }
 
@@ -129,92 +204,118 @@ pub fn is_not_pause_state(pause_state: Res<PauseStateRes>) -> bool {
     !pause_state.is_pause_state()
 }
 
-pub fn setup_snake_and_food(mut commands: Commands) {
+// the state set on a crash is only entered after the fixed update, which can run twice in a frame.
+pub fn is_not_round_ending(next_state: Res<NextState<InGameState>>) -> bool {
+    return !matches!(next_state.0, Some(InGameState::Dying | InGameState::GameOver));
+}
+
+pub fn setup_snake_and_food(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    snake_atlas: Res<SnakeAtlas>,
+    settings: Res<Settings>,
+    mut snake_body: ResMut<SnakeBody>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut food_rules: ResMut<FoodRules>,
+    mut food_respawn: ResMut<FoodRespawn>,
+) {
     let snake_default_pox_x = 0.;
     let snake_default_pox_y = 0.;
 
-    commands
+    // the direction is chosen while preparing, see prepare_system.
+    let head = spawn_snake_node(
+        &mut commands,
+        snake_default_pox_x,
+        snake_default_pox_y,
+        SnakeType::Unknown,
+        &theme,
+        &snake_atlas,
+    );
+    *snake_body = SnakeBody::default();
+    snake_body.grow(head, IVec2::ZERO);
+    occupancy.add_segment(IVec2::ZERO, PLAYER_ONE);
+
+    *food_rules = FoodRules {
+        count: settings.food_count,
+        lifetime: settings.food_expires.then_some(FOOD_LIFETIME_SECONDS),
+    };
+    food_respawn.0.clear();
+    for _ in 0..food_rules.count {
+        spawn_food(&mut commands, &mut occupancy, &theme, &food_rules);
+    }
+}
+
+// puts the food on a random free cell, there is no food when the snake fills the board.
+pub fn spawn_food(commands: &mut Commands, occupancy: &mut BoardOccupancy, theme: &Theme, rules: &FoodRules) {
+    let Some(cell) = occupancy.random_free_cell() else {
+        return;
+    };
+    let kind = FoodKind::Normal;
+    occupancy.set(cell, CellContent::Food(kind));
+    commands.spawn((create_food_bundle(cell, theme), Food {
+        kind,
+        cell,
+        lifetime: rules.lifetime.map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+    }));
+}
+
+// a snake node is drawn with a piece of the snake atlas, and with two colored quads until the atlas is loaded.
+pub fn spawn_snake_node(
+    commands: &mut Commands,
+    x: f32,
+    y: f32,
+    snake_type: SnakeType,
+    theme: &Theme,
+    snake_atlas: &SnakeAtlas,
+) -> Entity {
+    return commands
         .spawn((
-            create_snake_node_bundle(snake_default_pox_x, snake_default_pox_y),
-            SnakeNode {
-                // If no need to move when entering the game, snake_type can be set to: SnakeType::Unknown,
-                snake_type: SnakeType::Head(Direction::Down),
-            },
+            SpatialBundle::from_transform(Transform::from_xyz(x, y, 0.)),
+            SnakeNode { snake_type },
         ))
         .with_children(|parent| {
-            parent.spawn(create_snake_node_child_bundle());
-        });
-
-    commands.spawn((
-        create_food_bundle(&vec![(snake_default_pox_x, snake_default_pox_y)]),
-        Food,
-    ));
+            parent
+                .spawn((create_snake_node_bundle(theme), SnakeQuad))
+                .with_children(|parent| {
+                    parent.spawn(create_snake_node_child_bundle(theme));
+                });
+            parent.spawn((create_snake_piece_bundle(snake_atlas), SnakePiece));
+        })
+        .id();
 }
 
-pub fn create_snake_node_bundle(x: f32, y: f32) -> SpriteBundle {
-    let border_color = Color::rgba(0.7, 0.34, 0.85, 0.9);
-    SpriteBundle {
+pub fn create_snake_node_bundle(theme: &Theme) -> (SpriteBundle, ThemeColor) {
+    (SpriteBundle {
         sprite: Sprite {
-            color: border_color,
+            color: theme.snake_border,
             custom_size: Some(Vec2::new(SNAKE_NODE_SIZE, SNAKE_NODE_SIZE)),
             ..default()
         },
-        transform: Transform {
-            translation: Vec3::new(x, y, 0.),
-            ..default()
-        },
         ..default()
-    }
+    }, ThemeColor::SnakeBorder)
 }
 
-pub fn create_food_bundle(excepted_position: &Vec<(f32, f32)>) -> SpriteBundle {
-    let max_x = (BOUNDARY_WIDTH / 2. / SNAKE_NODE_SIZE) as i32;
-    let max_y = (BOUNDARY_HEIGHT / 2. / SNAKE_NODE_SIZE) as i32;
-
-    let mut rng = rand::thread_rng();
-    let mut x_rand: i32;
-    let mut y_rand: i32;
-
-    'outer: loop {
-        x_rand = rng.gen_range(-max_x..=max_x);
-        y_rand = rng.gen_range(-max_y..=max_y);
-
-        for &pos in excepted_position {
-            if (
-                (x_rand as f32) * SNAKE_NODE_SIZE,
-                (y_rand as f32) * SNAKE_NODE_SIZE,
-            ) == pos
-            {
-                continue 'outer;
-            }
-        }
-        break 'outer;
-    }
-
-    SpriteBundle {
+pub fn create_food_bundle(cell: IVec2, theme: &Theme) -> (SpriteBundle, ThemeColor) {
+    let position = Board::cell_position(cell);
+    (SpriteBundle {
         sprite: Sprite {
-            color: Color::rgba_u8(200, 40, 28, 255),
+            color: theme.food,
             custom_size: Some(Vec2::new(SNAKE_NODE_SIZE - 4., SNAKE_NODE_SIZE - 4.)),
             ..default()
         },
         transform: Transform {
-            translation: Vec3::new(
-                x_rand as f32 * SNAKE_NODE_SIZE,
-                y_rand as f32 * SNAKE_NODE_SIZE,
-                0.,
-            ),
+            translation: position.extend(0.),
             ..default()
         },
         ..default()
-    }
+    }, ThemeColor::Food)
 }
 
-pub fn create_snake_node_child_bundle() -> SpriteBundle {
-    let bg_color = Color::rgb(0.25, 0.25, 0.75);
+pub fn create_snake_node_child_bundle(theme: &Theme) -> (SpriteBundle, ThemeColor) {
     let border_widh = 1.;
-    SpriteBundle {
+    (SpriteBundle {
         sprite: Sprite {
-            color: bg_color,
+            color: theme.snake_body,
             custom_size: Some(Vec2::new(
                 SNAKE_NODE_SIZE - border_widh,
                 SNAKE_NODE_SIZE - border_widh,
@@ -226,5 +327,5 @@ pub fn create_snake_node_child_bundle() -> SpriteBundle {
             ..default()
         },
         ..default()
-    }
+    }, ThemeColor::SnakeBody)
 }
@@ -0,0 +1,321 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::constants::*;
+use crate::data::component::{Hazard, SnakeLength, ThemeColor};
+use crate::data::occupancy::{BoardOccupancy, CellContent};
+use crate::data::snake_body::SnakeBody;
+use crate::data::states::InGameState;
+use crate::data::{
+    Board, DeathCause, Direction, GameOverEvent, HazardHitEvent, HazardKind, HazardPenalty, RoundMode,
+};
+use crate::settings::Settings;
+use crate::theme::Theme;
+use crate::update::cut_from;
+
+// puts the hazards of the level chosen in the settings on the board, after the portals and before the snake.
+pub fn setup_hazards(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    board: Res<Board>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut penalty: ResMut<HazardPenalty>,
+) {
+    *penalty = if settings.game_mode.can_die() {
+        settings.hazards.penalty()
+    } else {
+        HazardPenalty::LoseSegments(HAZARD_LOST_SEGMENTS)
+    };
+    for (kind, cell) in settings.hazards.layout(&board) {
+        if !occupancy.is_free(cell) {
+            continue;
+        }
+        occupancy.set(cell, CellContent::Hazard);
+        commands.spawn((create_hazard_bundle(&kind, cell, &theme), ThemeColor::Hazard, Hazard {
+            timer: Timer::from_seconds(hazard_seconds(&kind), TimerMode::Repeating),
+            kind,
+            cell,
+        }));
+    }
+}
+
+fn hazard_seconds(kind: &HazardKind) -> f32 {
+    return match kind {
+        HazardKind::Patrol { .. } => HAZARD_PATROL_SECONDS,
+        HazardKind::Ball { .. } => HAZARD_BALL_SECONDS,
+        HazardKind::Hunter => HAZARD_HUNTER_SECONDS,
+    };
+}
+
+// a block fills its cell, a ball is a small diamond and the hunter a smaller block.
+fn create_hazard_bundle(kind: &HazardKind, cell: IVec2, theme: &Theme) -> SpriteBundle {
+    let (size, angle) = match kind {
+        HazardKind::Patrol { .. } => (SNAKE_NODE_SIZE - 2., 0.),
+        HazardKind::Ball { .. } => (SNAKE_NODE_SIZE * 0.5, std::f32::consts::FRAC_PI_4),
+        HazardKind::Hunter => (SNAKE_NODE_SIZE * 0.7, 0.),
+    };
+    return SpriteBundle {
+        sprite: Sprite {
+            color: theme.hazard,
+            custom_size: Some(Vec2::splat(size)),
+            ..default()
+        },
+        transform: Transform::from_translation(Board::cell_position(cell).extend(0.))
+            .with_rotation(Quat::from_rotation_z(angle)),
+        ..default()
+    };
+}
+
+pub fn dismiss_hazards(mut commands: Commands, query: Query<Entity, With<Hazard>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// What a hazard does when its timer finishes.
+#[derive(PartialEq, Debug)]
+enum HazardStep {
+    Move(IVec2),
+    Hit, // it touches the snake instead of moving into it.
+    Wait,
+}
+
+// steps every hazard whose timer finished. a hazard only moves into an empty cell, it touches the snake
+// instead of moving into it, and turns around or waits when something else is in the way.
+pub fn hazard_move_system(
+    time: Res<Time>,
+    board: Res<Board>,
+    snake_body: Res<SnakeBody>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut hazard_hits: EventWriter<HazardHitEvent>,
+    mut query: Query<(&mut Hazard, &mut Transform)>,
+) {
+    for (mut hazard, mut transform) in &mut query {
+        if !hazard.timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let cell = hazard.cell;
+        let step = match &mut hazard.kind {
+            HazardKind::Patrol { path, index, forward } => patrol_step(path, index, forward, &occupancy),
+            HazardKind::Ball { velocity } => ball_step(cell, velocity, &board, &occupancy),
+            HazardKind::Hunter => match snake_body.head() {
+                Some((_, head)) => hunt(cell, head, &board, &occupancy),
+                None => HazardStep::Wait,
+            },
+        };
+        let next = match step {
+            HazardStep::Move(next) => next,
+            HazardStep::Hit => {
+                hazard_hits.send(HazardHitEvent);
+                continue;
+            }
+            HazardStep::Wait => continue,
+        };
+        occupancy.clear(cell);
+        occupancy.set(next, CellContent::Hazard);
+        hazard.cell = next;
+        let position = Board::cell_position(next);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+// the next cell on the path, the patrol turns around at the ends and when the way is blocked.
+fn patrol_step(path: &[IVec2], index: &mut usize, forward: &mut bool, occupancy: &BoardOccupancy) -> HazardStep {
+    if path.len() < 2 {
+        return HazardStep::Wait;
+    }
+    if (*forward && *index + 1 == path.len()) || (!*forward && *index == 0) {
+        *forward = !*forward;
+    }
+    let next = if *forward { *index + 1 } else { *index - 1 };
+    let step = match occupancy.get(path[next]) {
+        CellContent::Empty => {
+            *index = next;
+            return HazardStep::Move(path[next]);
+        }
+        CellContent::Snake(_) => HazardStep::Hit,
+        _ => HazardStep::Wait,
+    };
+    *forward = !*forward;
+    return step;
+}
+
+// the ball keeps its diagonal until something is in the way, then it tries the other diagonals,
+// the one mirrored on the side it hit first.
+fn ball_step(cell: IVec2, velocity: &mut IVec2, board: &Board, occupancy: &BoardOccupancy) -> HazardStep {
+    let v = *velocity;
+    for candidate in [v, IVec2::new(-v.x, v.y), IVec2::new(v.x, -v.y), -v] {
+        let next = board.wrapped(cell + candidate);
+        match occupancy.get(next) {
+            CellContent::Empty => {
+                *velocity = candidate;
+                return HazardStep::Move(next);
+            }
+            CellContent::Snake(_) => {
+                *velocity = -candidate;
+                return HazardStep::Hit;
+            }
+            _ => {}
+        }
+    }
+    return HazardStep::Wait;
+}
+
+// the first step of the shortest way through the empty cells to the head, it waits when there is no way.
+fn hunt(from: IVec2, head: IVec2, board: &Board, occupancy: &BoardOccupancy) -> HazardStep {
+    let mut visited = vec![false; (board.columns * board.rows) as usize];
+    // every cell is queued with the first step of the way to it.
+    let mut queue: VecDeque<(IVec2, Option<IVec2>)> = VecDeque::from([(from, None)]);
+    while let Some((cell, first)) = queue.pop_front() {
+        for direction in Direction::ALL {
+            let next = board.next_cell(cell, direction);
+            let Some(index) = board.cell_index(next) else {
+                continue;
+            };
+            if visited[index] || next == from {
+                continue;
+            }
+            let first_step = first.unwrap_or(next);
+            if next == head {
+                if first_step == head {
+                    return HazardStep::Hit;
+                }
+                return HazardStep::Move(first_step);
+            }
+            if occupancy.is_free(next) {
+                visited[index] = true;
+                queue.push_back((next, Some(first_step)));
+            }
+        }
+    }
+    return HazardStep::Wait;
+}
+
+// what touching a hazard costs, once a frame however often the snake and the hazards touched in it.
+pub fn hazard_hit_system(
+    mut commands: Commands,
+    mut hazard_hits: EventReader<HazardHitEvent>,
+    penalty: Res<HazardPenalty>,
+    round: Res<RoundMode>,
+    mut snake_body: ResMut<SnakeBody>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut score_query: Query<(&mut Text, &mut SnakeLength)>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+) {
+    if hazard_hits.read().count() == 0 {
+        return;
+    }
+    let Ok((mut text, mut len)) = score_query.get_single_mut() else {
+        return;
+    };
+    if let HazardPenalty::LoseSegments(count) = *penalty {
+        if snake_body.len() > 1 {
+            let index = snake_body.len().saturating_sub(count);
+            cut_from(&mut commands, &mut snake_body, &mut occupancy, (&mut text, &mut len), index);
+            return;
+        }
+    }
+    if !round.mode.can_die() {
+        return;
+    }
+    game_over_events.send(GameOverEvent {
+        cause: DeathCause::Hazard,
+        score: len.0,
+        length: snake_body.len(),
+    });
+    game_state.set(InGameState::Dying);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::occupancy::PLAYER_ONE;
+
+    fn board(wrap: bool) -> Board {
+        return Board {
+            columns: 9,
+            rows: 9,
+            wrap,
+        };
+    }
+
+    #[test]
+    fn the_hunter_takes_the_shortest_way() {
+        let board = board(false);
+        let occupancy = BoardOccupancy::new(board);
+        let step = hunt(IVec2::new(-3, 0), IVec2::new(3, 0), &board, &occupancy);
+        assert_eq!(step, HazardStep::Move(IVec2::new(-2, 0)));
+    }
+
+    #[test]
+    fn the_hunter_goes_around_a_wall() {
+        let board = board(false);
+        let mut occupancy = BoardOccupancy::new(board);
+        for y in -4..=3 {
+            occupancy.set(IVec2::new(0, y), CellContent::Wall);
+        }
+        let step = hunt(IVec2::new(-1, 0), IVec2::new(1, 0), &board, &occupancy);
+        assert_eq!(step, HazardStep::Move(IVec2::new(-1, 1)));
+    }
+
+    #[test]
+    fn the_hunter_next_to_the_head_hits_it() {
+        let board = board(false);
+        let occupancy = BoardOccupancy::new(board);
+        assert_eq!(hunt(IVec2::new(2, 0), IVec2::new(3, 0), &board, &occupancy), HazardStep::Hit);
+    }
+
+    #[test]
+    fn the_hunter_waits_when_there_is_no_way() {
+        let board = board(false);
+        let mut occupancy = BoardOccupancy::new(board);
+        for y in -4..=4 {
+            occupancy.set(IVec2::new(0, y), CellContent::Wall);
+        }
+        let step = hunt(IVec2::new(-2, 0), IVec2::new(2, 0), &board, &occupancy);
+        assert_eq!(step, HazardStep::Wait);
+    }
+
+    #[test]
+    fn the_ball_bounces_out_of_a_corner() {
+        let board = board(false);
+        let occupancy = BoardOccupancy::new(board);
+        let mut velocity = IVec2::ONE;
+        let step = ball_step(IVec2::new(4, 4), &mut velocity, &board, &occupancy);
+        assert_eq!(step, HazardStep::Move(IVec2::new(3, 3)));
+        assert_eq!(velocity, IVec2::NEG_ONE);
+    }
+
+    #[test]
+    fn the_ball_is_mirrored_on_the_side_it_hits() {
+        let board = board(false);
+        let occupancy = BoardOccupancy::new(board);
+        let mut velocity = IVec2::ONE;
+        let step = ball_step(IVec2::new(4, 0), &mut velocity, &board, &occupancy);
+        assert_eq!(step, HazardStep::Move(IVec2::new(3, 1)));
+        assert_eq!(velocity, IVec2::new(-1, 1));
+    }
+
+    #[test]
+    fn the_ball_crosses_a_wrapping_board() {
+        let board = board(true);
+        let occupancy = BoardOccupancy::new(board);
+        let mut velocity = IVec2::ONE;
+        let step = ball_step(IVec2::new(4, 0), &mut velocity, &board, &occupancy);
+        assert_eq!(step, HazardStep::Move(IVec2::new(-4, 1)));
+    }
+
+    #[test]
+    fn the_ball_hits_the_snake_and_turns_back() {
+        let board = board(false);
+        let mut occupancy = BoardOccupancy::new(board);
+        occupancy.add_segment(IVec2::new(1, 1), PLAYER_ONE);
+        let mut velocity = IVec2::ONE;
+        assert_eq!(ball_step(IVec2::ZERO, &mut velocity, &board, &occupancy), HazardStep::Hit);
+        assert_eq!(velocity, IVec2::NEG_ONE);
+    }
+}
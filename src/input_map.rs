@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::GAMEPAD_STICK_DEADZONE;
+use crate::data::Direction;
+use crate::touch::TouchState;
+
+// Everything the player can ask for with a key, the systems read actions instead of key codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    Pause, // pauses a running round and resumes a paused one.
+    Restart,
+    MainMenu,
+    Settings,
+    Back,
+    StartEasy,
+    StartNormal,
+    StartHard,
+    StartDefault, // starts with the difficulty chosen in the settings.
+    Quit,
+    Fullscreen,
+    GameMode, // the next mode in the main menu.
+    EndRound,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 17] = [
+        InputAction::TurnUp,
+        InputAction::TurnDown,
+        InputAction::TurnLeft,
+        InputAction::TurnRight,
+        InputAction::Pause,
+        InputAction::Restart,
+        InputAction::MainMenu,
+        InputAction::Settings,
+        InputAction::Back,
+        InputAction::StartEasy,
+        InputAction::StartNormal,
+        InputAction::StartHard,
+        InputAction::StartDefault,
+        InputAction::Quit,
+        InputAction::Fullscreen,
+        InputAction::GameMode,
+        InputAction::EndRound,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            InputAction::TurnUp => "Turn Up",
+            InputAction::TurnDown => "Turn Down",
+            InputAction::TurnLeft => "Turn Left",
+            InputAction::TurnRight => "Turn Right",
+            InputAction::Pause => "Pause",
+            InputAction::Restart => "Restart",
+            InputAction::MainMenu => "Main Menu",
+            InputAction::Settings => "Settings",
+            InputAction::Back => "Back",
+            InputAction::StartEasy => "Start Easy",
+            InputAction::StartNormal => "Start Normal",
+            InputAction::StartHard => "Start Hard",
+            InputAction::StartDefault => "Start Default",
+            InputAction::Quit => "Quit",
+            InputAction::Fullscreen => "Fullscreen",
+            InputAction::GameMode => "Game Mode",
+            InputAction::EndRound => "End Round",
+        };
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        return match self {
+            InputAction::TurnUp => vec![KeyCode::Up, KeyCode::W],
+            InputAction::TurnDown => vec![KeyCode::Down, KeyCode::S],
+            InputAction::TurnLeft => vec![KeyCode::Left, KeyCode::A],
+            InputAction::TurnRight => vec![KeyCode::Right, KeyCode::D],
+            InputAction::Pause => vec![KeyCode::Space],
+            InputAction::Restart => vec![KeyCode::R],
+            InputAction::MainMenu => vec![KeyCode::M],
+            InputAction::Settings => vec![KeyCode::O],
+            InputAction::Back => vec![KeyCode::B],
+            InputAction::StartEasy => vec![KeyCode::E],
+            InputAction::StartNormal => vec![KeyCode::N],
+            InputAction::StartHard => vec![KeyCode::H],
+            InputAction::StartDefault => vec![KeyCode::Return],
+            InputAction::Quit => vec![KeyCode::Q],
+            InputAction::Fullscreen => vec![KeyCode::F11],
+            InputAction::GameMode => vec![KeyCode::G],
+            InputAction::EndRound => vec![KeyCode::End],
+        };
+    }
+
+    // the gamepad buttons are fixed, the menus are reached by moving the focus with the D-pad.
+    fn gamepad_buttons(&self) -> &'static [GamepadButtonType] {
+        return match self {
+            InputAction::TurnUp => &[GamepadButtonType::DPadUp],
+            InputAction::TurnDown => &[GamepadButtonType::DPadDown],
+            InputAction::TurnLeft => &[GamepadButtonType::DPadLeft],
+            InputAction::TurnRight => &[GamepadButtonType::DPadRight],
+            InputAction::Pause => &[GamepadButtonType::Start],
+            InputAction::Back => &[GamepadButtonType::East],
+            _ => &[],
+        };
+    }
+}
+
+// Maps every action to the keys that trigger it, it is saved with the settings.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMap(pub BTreeMap<InputAction, Vec<KeyCode>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut map = BTreeMap::new();
+        for action in InputAction::ALL {
+            map.insert(action, action.default_keys());
+        }
+        return InputMap(map);
+    }
+}
+
+impl InputMap {
+    pub fn keys(&self, action: InputAction) -> &[KeyCode] {
+        return self.0.get(&action).map(|keys| keys.as_slice()).unwrap_or(&[]);
+    }
+
+    // the key moves to the action, other actions lose it so one key never means two things.
+    pub fn bind(&mut self, action: InputAction, key: KeyCode) {
+        for keys in self.0.values_mut() {
+            keys.retain(|it| *it != key);
+        }
+        self.0.insert(action, vec![key]);
+    }
+
+    // actions added after the settings file was written get their default keys.
+    pub fn fill_missing(&mut self) {
+        for action in InputAction::ALL {
+            if !self.0.contains_key(&action) {
+                let keys = action
+                    .default_keys()
+                    .into_iter()
+                    .filter(|key| !self.0.values().any(|keys| keys.contains(key)))
+                    .collect();
+                self.0.insert(action, keys);
+            }
+        }
+    }
+
+    // the first key of the action, it is shown in front of the menu buttons.
+    pub fn hint(&self, action: InputAction) -> String {
+        return match self.keys(action).first() {
+            Some(key) => key_name(*key),
+            None => "-".to_string(),
+        };
+    }
+
+    pub fn describe(&self, action: InputAction) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return "-".to_string();
+        }
+        return keys
+            .iter()
+            .map(|key| key_name(*key))
+            .collect::<Vec<String>>()
+            .join(" / ");
+    }
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    return match key {
+        KeyCode::Key1 => "1".to_string(),
+        KeyCode::Key2 => "2".to_string(),
+        KeyCode::Key3 => "3".to_string(),
+        KeyCode::Key4 => "4".to_string(),
+        KeyCode::Key5 => "5".to_string(),
+        KeyCode::Key6 => "6".to_string(),
+        KeyCode::Key7 => "7".to_string(),
+        KeyCode::Key8 => "8".to_string(),
+        KeyCode::Key9 => "9".to_string(),
+        KeyCode::Key0 => "0".to_string(),
+        KeyCode::Return => "Enter".to_string(),
+        _ => format!("{:?}", key),
+    };
+}
+
+// The action currently waiting for a new key on the key bindings screen.
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<InputAction>);
+
+// Reads the input of the player as actions, from the keyboard, every connected gamepad and the touch screen.
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    keyboard: Res<'w, Input<KeyCode>>,
+    input_map: Res<'w, InputMap>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    touch: Res<'w, TouchState>,
+}
+
+impl ActionInput<'_> {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        if self.keyboard.any_pressed(self.input_map.keys(action).iter().copied()) {
+            return true;
+        }
+        for gamepad in self.gamepads.iter() {
+            for button_type in action.gamepad_buttons() {
+                if self.gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type)) {
+                    return true;
+                }
+            }
+        }
+        let turn_direction = match action {
+            InputAction::TurnUp => Some(Direction::Up),
+            InputAction::TurnDown => Some(Direction::Down),
+            InputAction::TurnLeft => Some(Direction::Left),
+            InputAction::TurnRight => Some(Direction::Right),
+            _ => None,
+        };
+        if turn_direction.is_none() {
+            return false;
+        }
+        return self.stick_direction() == turn_direction || self.touch.direction() == turn_direction;
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        if self
+            .keyboard
+            .any_just_pressed(self.input_map.keys(action).iter().copied())
+        {
+            return true;
+        }
+        return action
+            .gamepad_buttons()
+            .iter()
+            .any(|button_type| self.gamepad_just_pressed(*button_type));
+    }
+
+    // a short touch that did not move, it is not an action because it also clicks the menu buttons.
+    pub fn tapped(&self) -> bool {
+        return self.touch.tap;
+    }
+
+    // the menus are walked with fixed keys, they are not in the key map because the arrows also steer.
+    // 1 moves the focus down, -1 moves it up and 0 keeps it.
+    pub fn menu_step(&self) -> i32 {
+        let shift = self.keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let tab = self.keyboard.just_pressed(KeyCode::Tab);
+        if self.keyboard.just_pressed(KeyCode::Down)
+            || (tab && !shift)
+            || self.gamepad_just_pressed(GamepadButtonType::DPadDown)
+        {
+            return 1;
+        }
+        if self.keyboard.just_pressed(KeyCode::Up)
+            || (tab && shift)
+            || self.gamepad_just_pressed(GamepadButtonType::DPadUp)
+        {
+            return -1;
+        }
+        return 0;
+    }
+
+    // clicks the focused menu button.
+    pub fn menu_confirm(&self) -> bool {
+        return self.keyboard.any_just_pressed([KeyCode::Return, KeyCode::NumpadEnter])
+            || self.gamepad_just_pressed(GamepadButtonType::South);
+    }
+
+    pub fn gamepad_just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        return self
+            .gamepads
+            .iter()
+            .any(|gamepad| self.gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)));
+    }
+
+    // the direction the left stick of any gamepad points to, the stronger axis wins.
+    pub fn stick_direction(&self) -> Option<Direction> {
+        for gamepad in self.gamepads.iter() {
+            let x = self
+                .gamepad_axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                .unwrap_or(0.);
+            let y = self
+                .gamepad_axes
+                .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY))
+                .unwrap_or(0.);
+            if x.abs() < GAMEPAD_STICK_DEADZONE && y.abs() < GAMEPAD_STICK_DEADZONE {
+                continue;
+            }
+            if x.abs() > y.abs() {
+                return Some(if x > 0. { Direction::Right } else { Direction::Left });
+            }
+            return Some(if y > 0. { Direction::Up } else { Direction::Down });
+        }
+        return None;
+    }
+}
@@ -4,210 +4,386 @@ use crate::{
     constants::*,
     data::{
         component::{MenuButtonAction, SnakeNode},
-        states::{AppState, InGameState},
-        PauseStateRes, SnakeType,
+        states::{AppState, InGameState, SettingsState},
+        snake_body::SnakeBody,
+        Board, Countdown, Difficulty, PauseStateRes, Portals, SnakeType, SoundEffect,
     },
+    focus::FocusedButton,
+    input_map::{ActionInput, InputAction, Rebinding},
+    settings::{is_settings_hotkey, save_settings, SettingItem, Settings, KEY_BINDINGS_HOTKEY},
 };
 
 use crate::data::Direction;
 
 pub fn change_direction_key_event(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut Transform, &mut SnakeNode)>,
-    pause_state: Res<PauseStateRes>,
+    action_input: ActionInput,
+    mut query: Query<&mut SnakeNode>,
     cur_game_state: Res<State<InGameState>>,
+    board: Res<Board>,
+    portals: Res<Portals>,
+    snake_body: Res<SnakeBody>,
+    mut sound_effects: EventWriter<SoundEffect>,
 ) {
-    if pause_state.is_pause_state() || *cur_game_state.get() != InGameState::Playing {
+    // the first direction can already be chosen while preparing.
+    if *cur_game_state.get() != InGameState::Playing
+        && *cur_game_state.get() != InGameState::Preparing
+    {
         return;
     }
     let mut direction: Option<Direction> = None;
-    if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
+    if action_input.pressed(InputAction::TurnLeft) {
         direction = Some(Direction::Left);
     }
-    if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
+    if action_input.pressed(InputAction::TurnRight) {
         direction = Some(Direction::Right);
     }
-    if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
+    if action_input.pressed(InputAction::TurnUp) {
         direction = Some(Direction::Up);
     }
-    if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
+    if action_input.pressed(InputAction::TurnDown) {
         direction = Some(Direction::Down);
     }
-    if None == direction {
+    if direction.is_none() {
         return;
     }
 
-    let mut vec = Vec::from_iter(query.iter_mut());
-    vec.reverse();
-    // find the head node
-    let head = (&vec)
-        .into_iter()
-        .find(|item| {
-            if let SnakeType::Head(_) = item.1.snake_type {
-                return true;
-            }
-            if SnakeType::Unknown == item.1.snake_type {
-                return true;
-            }
-            return false;
-        })
-        .unwrap();
-
-    let mut snake_node_after_head_x = 0.;
-    let mut snake_node_after_head_y = 0.;
-
-    // find the position of the node after the head
-    for snake_node in &vec {
-        if SnakeType::Body == snake_node.1.snake_type {
-            snake_node_after_head_x = snake_node.0.translation.x;
-            snake_node_after_head_y = snake_node.0.translation.y;
-            break;
+    let Some((head, head_cell)) = snake_body.head() else {
+        return;
+    };
+    let dir = direction.take().unwrap();
+    // stop change the move direction if the head would turn back into the node after it
+    if let Some((_, neck_cell)) = snake_body.get(1) {
+        if portals.next_cell(&board, head_cell, dir) == neck_cell {
+            return;
         }
     }
 
-    let dir = direction.take().unwrap();
-    // stop change the move direction in this circumstances
-    if dir == Direction::Right
-        && snake_node_after_head_x == head.0.translation.x + SNAKE_NODE_SIZE
-        && snake_node_after_head_y == head.0.translation.y
-    {
-        return;
+    // set the moving direction of the head, only on a turn so a held key does not change the node every frame.
+    if let Ok(mut node) = query.get_mut(head) {
+        match node.snake_type {
+            SnakeType::Head(current) if current != dir => {
+                sound_effects.send(SoundEffect::Turn);
+                node.snake_type = SnakeType::Head(dir);
+            }
+            SnakeType::Unknown => node.snake_type = SnakeType::Head(dir),
+            _ => {}
+        }
     }
-    if dir == Direction::Left
-        && snake_node_after_head_x == head.0.translation.x - SNAKE_NODE_SIZE
-        && snake_node_after_head_y == head.0.translation.y
-    {
+}
+
+pub fn game_state_key_event(
+    action_input: ActionInput,
+    cur_game_state: Res<State<InGameState>>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut pause_state: ResMut<PauseStateRes>,
+    mut countdown: ResMut<Countdown>,
+    settings_state: Res<State<SettingsState>>,
+) {
+    if *settings_state.get() != SettingsState::Closed {
         return;
     }
-    if dir == Direction::Up
-        && snake_node_after_head_x == head.0.translation.x
-        && snake_node_after_head_y == head.0.translation.y + SNAKE_NODE_SIZE
-    {
+    // a tap on the touch screen only pauses, the paused screen is left with its buttons.
+    if action_input.tapped() && *cur_game_state.get() == InGameState::Playing {
+        pause_state.user_click_pause = true;
+        game_state.set(InGameState::Paused);
         return;
     }
-    if dir == Direction::Down
-        && snake_node_after_head_x == head.0.translation.x
-        && snake_node_after_head_y == head.0.translation.y - SNAKE_NODE_SIZE
-    {
+    if !action_input.just_pressed(InputAction::Pause) {
         return;
     }
-
-    // find the head again, set the moving direction
-    if let Some(mut node) = query.iter_mut().last() {
-        if let SnakeType::Head(_) = node.1.snake_type {
-            node.1.snake_type = SnakeType::Head(dir);
+    match *cur_game_state.get() {
+        InGameState::Playing => {
+            pause_state.user_click_pause = true;
+            game_state.set(InGameState::Paused);
         }
-        if SnakeType::Unknown == node.1.snake_type {
-            node.1.snake_type = SnakeType::Head(dir);
+        InGameState::Paused if !countdown.is_running() => {
+            countdown.start(RESUME_COUNTDOWN_SECONDS);
         }
+        _ => {}
     }
 }
 
-pub fn game_state_key_event(
-    keyboard_input: Res<Input<KeyCode>>,
-    cur_game_state: ResMut<State<InGameState>>,
-    mut pause_state: ResMut<PauseStateRes>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        if *cur_game_state.get() == InGameState::Playing {
-            pause_state.user_click_pause = !pause_state.user_click_pause;
-        }
-    }
+// whether the button of the action belongs to the screen in front, the settings screens cover the others.
+pub fn is_action_on_top(settings_state: SettingsState, action: MenuButtonAction) -> bool {
+    return match settings_state {
+        SettingsState::Closed => true,
+        SettingsState::Open => matches!(
+            action,
+            MenuButtonAction::CycleSetting(_)
+                | MenuButtonAction::OpenKeyBindings
+                | MenuButtonAction::CloseSettings
+        ),
+        SettingsState::KeyBindings => matches!(
+            action,
+            MenuButtonAction::Rebind(_)
+                | MenuButtonAction::ResetKeyBindings
+                | MenuButtonAction::CloseKeyBindings
+        ),
+    };
 }
 
 pub fn button_click_system(
-    mut interaction_query: Query<
-        (&Interaction, &MenuButtonAction),
-        (Changed<Interaction>, With<Button>),
-    >,
+    interaction_query: Query<(&Interaction, &MenuButtonAction), (Changed<Interaction>, With<Button>)>,
+    focused_button: FocusedButton,
+    action_input: ActionInput,
     keyboard_input: Res<Input<KeyCode>>,
     mut app_state: ResMut<NextState<AppState>>,
-    cur_app_state: ResMut<State<AppState>>,
+    cur_app_state: Res<State<AppState>>,
     mut game_state: ResMut<NextState<InGameState>>,
-    cur_game_state: ResMut<State<InGameState>>,
+    cur_game_state: Res<State<InGameState>>,
     mut exit: EventWriter<AppExit>,
-    mut speed: ResMut<Time::<Fixed>>,
+    mut speed: ResMut<Time<Fixed>>,
+    mut pause_state: ResMut<PauseStateRes>,
+    mut countdown: ResMut<Countdown>,
+    cur_settings_state: Res<State<SettingsState>>,
+    mut settings_state: ResMut<NextState<SettingsState>>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
 ) {
-    let mut start_game_action = |level: i32| {
-        let mut start_game = false;
-        if AppState::MainMenu == *cur_app_state.as_ref().get() {
-            start_game = true;
+    let mut action: Option<MenuButtonAction> = None;
 
-            app_state.set(AppState::InGame);
-            game_state.set(InGameState::Playing);
+    match *cur_settings_state.get() {
+        SettingsState::Open => {
+            // the settings screen covers the other menus, only its own keys work.
+            for item in SettingItem::ALL {
+                if keyboard_input.just_pressed(item.hotkey()) {
+                    action = Some(MenuButtonAction::CycleSetting(item));
+                }
+            }
+            if keyboard_input.just_pressed(KEY_BINDINGS_HOTKEY) {
+                action = Some(MenuButtonAction::OpenKeyBindings);
+            }
+            if action_input.just_pressed(InputAction::Back) {
+                action = Some(MenuButtonAction::CloseSettings);
+            }
         }
-        if AppState::InGame == *cur_app_state.as_ref().get() {
-            match *cur_game_state.as_ref().get() {
-                InGameState::GameOver => {
-                    start_game = true;
-                    game_state.set(InGameState::Playing);
+        SettingsState::KeyBindings => {
+            if rebinding.0.is_none() && action_input.just_pressed(InputAction::Back) {
+                action = Some(MenuButtonAction::CloseKeyBindings);
+            }
+        }
+        SettingsState::Closed => {
+            // if the menu is showing, the start and quit actions can be used
+            if *cur_app_state.get() == AppState::MainMenu
+                || *cur_game_state.get() == InGameState::GameOver
+            {
+                if action_input.just_pressed(InputAction::StartEasy) {
+                    action = Some(MenuButtonAction::StartGameLevel1);
+                } else if action_input.just_pressed(InputAction::StartNormal) {
+                    action = Some(MenuButtonAction::StartGameLevel2);
+                } else if action_input.just_pressed(InputAction::StartHard) {
+                    action = Some(MenuButtonAction::StartGameLevel3);
+                } else if action_input.just_pressed(InputAction::StartDefault) {
+                    action = Some(MenuButtonAction::StartDefaultGame);
+                } else if action_input.just_pressed(InputAction::Quit) {
+                    action = Some(MenuButtonAction::Quit);
                 }
-                _ => {}
+            }
+            if *cur_app_state.get() == AppState::MainMenu && action_input.just_pressed(InputAction::GameMode) {
+                action = Some(MenuButtonAction::CycleGameMode);
+            }
+            if *cur_game_state.get() == InGameState::Paused && action_input.just_pressed(InputAction::EndRound) {
+                action = Some(MenuButtonAction::EndRound);
+            }
+            if (*cur_app_state.get() == AppState::MainMenu
+                || *cur_game_state.get() == InGameState::Paused)
+                && action_input.just_pressed(InputAction::Settings)
+            {
+                action = Some(MenuButtonAction::OpenSettings);
+            }
+            if *cur_game_state.get() == InGameState::Paused
+                && action_input.just_pressed(InputAction::Restart)
+            {
+                action = Some(MenuButtonAction::Restart);
+            }
+            if (*cur_game_state.get() == InGameState::Paused
+                || *cur_game_state.get() == InGameState::GameOver)
+                && action_input.just_pressed(InputAction::MainMenu)
+            {
+                action = Some(MenuButtonAction::MainMenu);
             }
         }
-        if start_game {
-            let new_speed = match level {
-                0 => EASY_MODE_FIXED_TIMESTEP,
-                1 => NORMAL_MODE_FIXED_TIMESTEP,
-                2 => HARD_MODE_FIXED_TIMESTEP,
-                _ => NORMAL_MODE_FIXED_TIMESTEP,
-            };
-            speed.set_timestep_seconds(new_speed);
+    }
+
+    for (interaction, button_action) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            action = Some(*button_action);
         }
+    }
+    // Enter or the A button of a gamepad clicks the focused button, it wins over the key of its action.
+    if action_input.menu_confirm() && focused_button.action().is_some() {
+        action = focused_button.action();
+    }
+
+    let Some(action) = action else {
+        return;
     };
 
-    let mut exit_game_action = || {
-        #[cfg(not(target_arch = "wasm32"))]
+    // the buttons under the settings screen can still be clicked, ignore them.
+    if !is_action_on_top(*cur_settings_state.get(), action) {
+        return;
+    }
+
+    let default_difficulty = settings.difficulty;
+    let mut start_game_action = |difficulty: Difficulty| {
+        let mut start_game = false;
+        if AppState::MainMenu == *cur_app_state.get() {
+            start_game = true;
+
+            app_state.set(AppState::InGame);
+            game_state.set(InGameState::Preparing);
+        }
+        if AppState::InGame == *cur_app_state.get()
+            && InGameState::GameOver == *cur_game_state.get()
         {
-            exit.send_default();
+            start_game = true;
+            game_state.set(InGameState::Preparing);
+        }
+        if start_game {
+            speed.set_timestep_seconds(difficulty.fixed_timestep());
         }
     };
 
-    // if the menu is showing, the S and Q can be clicked
-    if *cur_app_state.get() == AppState::MainMenu || *cur_game_state.get() == InGameState::GameOver
-    {
-        if keyboard_input.just_pressed(KeyCode::E) {
-            start_game_action(0);
-            return;
+    match action {
+        MenuButtonAction::StartGameLevel1 => {
+            start_game_action(Difficulty::Easy);
         }
-        if keyboard_input.just_pressed(KeyCode::N) {
-            start_game_action(1);
-            return;
+        MenuButtonAction::StartGameLevel2 => {
+            start_game_action(Difficulty::Normal);
         }
-        if keyboard_input.just_pressed(KeyCode::H) {
-            start_game_action(2);
-            return;
+        MenuButtonAction::StartGameLevel3 => {
+            start_game_action(Difficulty::Hard);
         }
-        if keyboard_input.just_pressed(KeyCode::Q) {
-            exit_game_action();
-            return;
+        MenuButtonAction::StartDefaultGame => {
+            start_game_action(default_difficulty);
+        }
+        MenuButtonAction::CycleGameMode => {
+            if AppState::MainMenu == *cur_app_state.get() {
+                settings.game_mode = settings.game_mode.next();
+                save_settings(&settings);
+            }
+        }
+        MenuButtonAction::Resume => {
+            if *cur_game_state.get() == InGameState::Paused && !countdown.is_running() {
+                countdown.start(RESUME_COUNTDOWN_SECONDS);
+            }
+        }
+        MenuButtonAction::Restart => {
+            // keep the current speed, only rebuild the board.
+            if *cur_game_state.get() == InGameState::Paused {
+                pause_state.clear();
+                countdown.cancel();
+                game_state.set(InGameState::Preparing);
+            }
+        }
+        MenuButtonAction::EndRound => {
+            // the round is recorded like a lost one, see record_ended_round_system.
+            if *cur_game_state.get() == InGameState::Paused {
+                pause_state.clear();
+                countdown.cancel();
+                game_state.set(InGameState::GameOver);
+            }
+        }
+        MenuButtonAction::MainMenu => {
+            // the in-game entities are cleaned up when leaving AppState::InGame.
+            if *cur_app_state.get() == AppState::InGame {
+                pause_state.clear();
+                countdown.cancel();
+                app_state.set(AppState::MainMenu);
+                game_state.set(InGameState::Idle);
+            }
+        }
+        MenuButtonAction::OpenSettings => {
+            settings_state.set(SettingsState::Open);
+        }
+        MenuButtonAction::CloseSettings => {
+            settings_state.set(SettingsState::Closed);
+        }
+        MenuButtonAction::CycleSetting(item) => {
+            settings.cycle(item);
+            save_settings(&settings);
+        }
+        MenuButtonAction::OpenKeyBindings => {
+            settings_state.set(SettingsState::KeyBindings);
+        }
+        MenuButtonAction::CloseKeyBindings => {
+            rebinding.0 = None;
+            settings_state.set(SettingsState::Open);
+        }
+        MenuButtonAction::Rebind(input_action) => {
+            // a second click on the same row cancels the rebinding.
+            if rebinding.0 == Some(input_action) {
+                rebinding.0 = None;
+            } else {
+                rebinding.0 = Some(input_action);
+            }
+        }
+        MenuButtonAction::ResetKeyBindings => {
+            rebinding.0 = None;
+            settings.key_bindings = Default::default();
+            save_settings(&settings);
+        }
+        MenuButtonAction::Quit => {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                exit.send_default();
+            }
         }
     }
+}
 
-    for (interaction, action) in &mut interaction_query {
-        if *interaction == Interaction::Pressed {
-            match *action {
-                MenuButtonAction::StartGameLevel1 => {
-                    start_game_action(0);
-                }
-                MenuButtonAction::StartGameLevel2 => {
-                    start_game_action(1);
-                }
-                MenuButtonAction::StartGameLevel3 => {
-                    start_game_action(2);
+pub fn window_focus_change_system(
+    mut events: EventReader<WindowFocused>,
+    cur_game_state: Res<State<InGameState>>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut pause_state: ResMut<PauseStateRes>,
+    mut countdown: ResMut<Countdown>,
+) {
+    for event in events.read() {
+        match *cur_game_state.get() {
+            InGameState::Playing => {
+                if !event.focused {
+                    pause_state.lose_focus_pause = true;
+                    game_state.set(InGameState::Paused);
                 }
-                MenuButtonAction::Quit => {
-                    exit_game_action();
+            }
+            InGameState::Paused => {
+                if !event.focused {
+                    pause_state.lose_focus_pause = true;
+                    countdown.cancel();
+                } else if pause_state.lose_focus_pause && !pause_state.user_click_pause {
+                    // the player did not ask for this pause, so resume on its own.
+                    countdown.start(RESUME_COUNTDOWN_SECONDS);
                 }
             }
+            // the start countdown stands still while the window has no focus, see prepare_system.
+            InGameState::Preparing => pause_state.lose_focus_pause = !event.focused,
+            _ => {}
         }
     }
 }
 
-pub fn window_focus_change_system(mut events: EventReader<WindowFocused>, mut pause_state: ResMut<PauseStateRes>,
+// binds the next pressed key to the action waiting on the key bindings screen.
+pub fn rebind_key_system(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
 ) {
-    for event in events.read() {
-        pause_state.lose_focus_pause = !event.focused;
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+        return;
+    };
+    // the key is used up here, the menu must not react to it in the same frame.
+    keyboard_input.clear_just_pressed(key);
+    // Back would close the settings screen instead of cycling the row, the screen waits for another key.
+    if action == InputAction::Back && is_settings_hotkey(key) {
+        return;
     }
-
-}
\ No newline at end of file
+    if key != KeyCode::Escape {
+        settings.key_bindings.bind(action, key);
+        save_settings(&settings);
+    }
+    rebinding.0 = None;
+}
@@ -0,0 +1,151 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::constants::*;
+use crate::data::component::{Particle, Pulse, Scatter};
+use crate::data::{FoodEatenEvent, GameOverEvent, ScreenShake};
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+// the particles fly over the snake.
+const PARTICLE_Z: f32 = 5.;
+
+// bursts the eaten food into particles and pulses the new head.
+pub fn food_eaten_juice_system(
+    mut commands: Commands,
+    mut events: EventReader<FoodEatenEvent>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    if !settings.effects {
+        events.clear();
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for event in events.read() {
+        for i in 0..PARTICLE_COUNT {
+            let angle = TAU * i as f32 / PARTICLE_COUNT as f32 + rng.gen_range(-0.2..0.2);
+            let speed = PARTICLE_SPEED * rng.gen_range(0.6..1.2);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: theme.food,
+                        custom_size: Some(Vec2::splat(PARTICLE_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(event.position.extend(PARTICLE_Z)),
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    timer: Timer::from_seconds(PARTICLE_SECONDS, TimerMode::Once),
+                },
+            ));
+        }
+        // the head is spawned in the same frame, it can be gone if the round was left.
+        if let Some(mut head) = commands.get_entity(event.head) {
+            head.insert(Pulse(Timer::from_seconds(PULSE_SECONDS, TimerMode::Once)));
+        }
+    }
+}
+
+// shakes the camera on the crash, not when the time of the round is up.
+pub fn game_over_juice_system(
+    mut events: EventReader<GameOverEvent>,
+    settings: Res<Settings>,
+    mut screen_shake: ResMut<ScreenShake>,
+) {
+    let crashed = events.read().filter(|event| event.cause.is_crash()).count() > 0;
+    if crashed && settings.effects {
+        screen_shake.start();
+    }
+}
+
+// throws a segment of the crashed snake off the board, away from its center and a little random
+// so the segments do not line up.
+pub fn scatter(transform: &Transform) -> Scatter {
+    let mut rng = rand::thread_rng();
+    let outward = transform.translation.truncate().normalize_or_zero();
+    let angle = outward.y.atan2(outward.x) + rng.gen_range(-0.6..0.6);
+    let speed = SCATTER_SPEED * rng.gen_range(0.5..1.0);
+    return Scatter {
+        velocity: Vec2::from_angle(angle) * speed,
+        spin: rng.gen_range(-TAU..TAU),
+    };
+}
+
+pub fn particle_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut Sprite, &mut Particle)>,
+) {
+    for (entity, mut transform, mut sprite, mut particle) in &mut query {
+        particle.timer.tick(time.delta());
+        if particle.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        sprite.color.set_a(1. - particle.timer.percent());
+    }
+}
+
+pub fn pulse_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Transform, &mut Pulse)>,
+) {
+    for (entity, mut transform, mut pulse) in &mut query {
+        pulse.0.tick(time.delta());
+        if pulse.0.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Pulse>();
+            continue;
+        }
+        // swells and shrinks back once.
+        let scale = 1. + PULSE_SCALE * (PI * pulse.0.percent()).sin();
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+// the segments slow down as they fly.
+pub fn scatter_system(time: Res<Time>, mut query: Query<(&mut Transform, &mut Scatter)>) {
+    let delta = time.delta_seconds();
+    for (mut transform, mut scatter) in &mut query {
+        transform.translation += (scatter.velocity * delta).extend(0.);
+        transform.rotate_z(scatter.spin * delta);
+        scatter.velocity *= (-3. * delta).exp();
+        scatter.spin *= (-3. * delta).exp();
+    }
+}
+
+pub fn screen_shake_system(
+    time: Res<Time>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    if !screen_shake.is_running() {
+        return;
+    }
+    screen_shake.remaining -= time.delta_seconds();
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+    if !screen_shake.is_running() {
+        transform.translation.x = 0.;
+        transform.translation.y = 0.;
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let strength = SHAKE_STRENGTH * screen_shake.remaining / SHAKE_SECONDS;
+    transform.translation.x = rng.gen_range(-1.0..1.0) * strength;
+    transform.translation.y = rng.gen_range(-1.0..1.0) * strength;
+}
+
+pub fn dismiss_particles(mut commands: Commands, query: Query<Entity, With<Particle>>) {
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode, WindowResized};
+
+use crate::constants::*;
+use crate::data::Board;
+use crate::input_map::{ActionInput, InputAction};
+
+// fits the board and the menus in the window again when it is resized or a round gets another board.
+// the camera makes the board as large as possible, the ui is scaled on its own so the menus always fit,
+// a phone held upright gets a smaller board instead of a cut one.
+pub fn fit_layout_system(
+    mut resized_events: EventReader<WindowResized>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    board: Res<Board>,
+    mut ui_scale: ResMut<UiScale>,
+    mut projections: Query<&mut OrthographicProjection>,
+) {
+    let resized = resized_events.read().count() > 0;
+    if !resized && !board.is_changed() {
+        return;
+    }
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    if window.width() <= 0. || window.height() <= 0. {
+        return;
+    }
+    let board_scale = (window.width() / (board.width() + 2. * BOARD_MARGIN))
+        .min(window.height() / (board.height() + 2. * SCORE_HEIGHT));
+    let fitted_ui_scale = (window.width() / LAYOUT_MIN_WIDTH).min(window.height() / LAYOUT_MIN_HEIGHT);
+    // only write on a change, the ui is laid out again for every change of UiScale.
+    if ui_scale.0 != fitted_ui_scale as f64 {
+        ui_scale.0 = fitted_ui_scale as f64;
+    }
+    for mut projection in &mut projections {
+        if projection.scale != 1. / board_scale {
+            projection.scale = 1. / board_scale;
+        }
+    }
+}
+
+pub fn toggle_fullscreen_system(
+    action_input: ActionInput,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !action_input.just_pressed(InputAction::Fullscreen) {
+        return;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    window.mode = match window.mode {
+        WindowMode::Windowed => WindowMode::BorderlessFullscreen,
+        _ => WindowMode::Windowed,
+    };
+}
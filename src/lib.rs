@@ -0,0 +1,235 @@
+#![allow(clippy::needless_return, clippy::too_many_arguments, clippy::type_complexity)]
+
+use bevy::{prelude::*, ui::UiSystem};
+
+use constants::*;
+use data::{
+    occupancy::BoardOccupancy,
+    snake_body::SnakeBody,
+    states::{AppState, InGameState, SettingsState},
+    Board, Countdown, DeathAnimation, FoodEatenEvent, FoodRespawn, FoodRules, GameOverEvent, HazardHitEvent,
+    HazardPenalty, PauseStateRes, Portals, RoundMode, RunStats, ScreenShake, SoundEffect,
+};
+use dying::{dying_system, enter_dying_system};
+use food::{food_expiry_system, food_respawn_system};
+use focus::{focus_highlight_system, menu_focus_system, MenuFocus};
+use game::{
+    dismiss_boundary, dismiss_snake_and_food, is_not_pause_state, is_not_round_ending, setup_boundary,
+    setup_camera, setup_snake_and_food, setup_snake_score,
+};
+use hazard::{dismiss_hazards, hazard_hit_system, hazard_move_system, setup_hazards};
+use interaction::*;
+use menu::{
+    enter_game_over_menu_system, enter_key_bindings_menu_system, enter_main_menu_system,
+    enter_pause_menu_system, enter_prepare_screen_system, enter_settings_menu_system,
+    exit_game_over_menu_system, exit_key_bindings_menu_system, exit_main_menu_system,
+    exit_pause_menu_system, exit_prepare_screen_system, exit_settings_menu_system,
+    pause_countdown_system, prepare_system, update_button_labels_system,
+};
+use input_map::{InputMap, Rebinding};
+use juice::{
+    dismiss_particles, food_eaten_juice_system, game_over_juice_system, particle_system,
+    pulse_system, scatter_system, screen_shake_system,
+};
+use layout::{fit_layout_system, toggle_fullscreen_system};
+use mode::{dismiss_mode, setup_mode_system, survival_system, time_attack_system};
+use records::{
+    load_records_system, record_ended_round_system, record_game_over_system, reset_run_stats_system,
+    run_stats_system, Records,
+};
+use settings::{apply_settings_system, load_settings_system, Settings};
+use snake_sprite::{load_snake_atlas_system, snake_sprite_system, SnakeAtlas};
+use theme::{
+    apply_theme_system, load_themes_system, select_theme_system, Theme, ThemeHandles, ThemeLoader,
+};
+use touch::{dismiss_touch_dpad, setup_touch_dpad, touch_input_system, TouchState};
+use update::{should_eat_food, snake_move};
+
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod constants;
+pub mod data;
+pub mod dying;
+pub mod focus;
+pub mod food;
+pub mod game;
+pub mod hazard;
+pub mod input_map;
+pub mod interaction;
+pub mod juice;
+pub mod layout;
+pub mod menu;
+pub mod menu_builder;
+pub mod mode;
+pub mod records;
+pub mod settings;
+pub mod snake_sprite;
+pub mod storage;
+pub mod theme;
+pub mod touch;
+pub mod update;
+
+// The whole game, main.rs adds it to an app with a window.
+pub struct SnakePlugin;
+
+impl Plugin for SnakePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.add_state::<AppState>()
+            .add_state::<InGameState>()
+            .add_state::<SettingsState>()
+            .insert_resource(PauseStateRes::new(false, false))
+            .init_resource::<Countdown>()
+            .init_resource::<Settings>()
+            .init_resource::<Board>()
+            .init_resource::<InputMap>()
+            .init_resource::<Rebinding>()
+            .init_resource::<MenuFocus>()
+            .init_resource::<TouchState>()
+            .init_resource::<RunStats>()
+            .init_resource::<Records>()
+            .init_resource::<Theme>()
+            .init_resource::<ThemeHandles>()
+            .init_resource::<SnakeAtlas>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<DeathAnimation>()
+            .init_resource::<SnakeBody>()
+            .init_resource::<BoardOccupancy>()
+            .init_resource::<Portals>()
+            .init_resource::<HazardPenalty>()
+            .init_resource::<FoodRules>()
+            .init_resource::<FoodRespawn>()
+            .init_resource::<RoundMode>()
+            .init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .add_event::<GameOverEvent>()
+            .add_event::<SoundEffect>()
+            .add_event::<FoodEatenEvent>()
+            .add_event::<HazardHitEvent>()
+            .add_systems(
+                Startup,
+                (
+                    setup_camera,
+                    load_settings_system,
+                    load_records_system,
+                    load_themes_system,
+                    load_snake_atlas_system,
+                ),
+            )
+            .add_systems(
+                OnExit(AppState::InGame),
+                (
+                    dismiss_boundary,
+                    dismiss_snake_and_food,
+                    dismiss_hazards,
+                    dismiss_mode,
+                    dismiss_touch_dpad,
+                    dismiss_particles,
+                ),
+            )
+            .add_systems(OnEnter(SettingsState::Open), enter_settings_menu_system)
+            .add_systems(OnExit(SettingsState::Open), exit_settings_menu_system)
+            .add_systems(OnEnter(SettingsState::KeyBindings), enter_key_bindings_menu_system)
+            .add_systems(OnExit(SettingsState::KeyBindings), exit_key_bindings_menu_system)
+            .add_systems(OnEnter(InGameState::Dying), enter_dying_system)
+            .add_systems(
+                OnEnter(InGameState::GameOver),
+                (record_ended_round_system, enter_game_over_menu_system).chain(),
+            )
+            .add_systems(OnExit(InGameState::GameOver), exit_game_over_menu_system)
+            .add_systems(OnEnter(InGameState::Paused), enter_pause_menu_system)
+            .add_systems(OnExit(InGameState::Paused), exit_pause_menu_system)
+            .add_systems(
+                OnEnter(InGameState::Preparing),
+                (
+                    dismiss_boundary,
+                    dismiss_snake_and_food,
+                    dismiss_hazards,
+                    dismiss_mode,
+                    dismiss_touch_dpad,
+                    dismiss_particles,
+                    apply_deferred,
+                    setup_mode_system,
+                    setup_boundary,
+                    setup_hazards,
+                    setup_snake_score,
+                    setup_snake_and_food,
+                    setup_touch_dpad,
+                    reset_run_stats_system,
+                    enter_prepare_screen_system,
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(InGameState::Preparing), exit_prepare_screen_system)
+            .add_systems(OnEnter(AppState::MainMenu), enter_main_menu_system)
+            .add_systems(OnExit(AppState::MainMenu), exit_main_menu_system)
+            // the touch system needs the D-pad buttons pressed in this frame.
+            .add_systems(PreUpdate, touch_input_system.after(UiSystem::Focus))
+            .add_systems(
+                FixedUpdate,
+                (should_eat_food, apply_deferred, snake_move)
+                    .chain()
+                    .run_if(is_not_pause_state.and_then(is_not_round_ending).and_then(
+                        in_state(AppState::InGame).and_then(in_state(InGameState::Playing)),
+                    )),
+            )
+            .add_systems(
+                Update,
+                (
+                    change_direction_key_event,
+                    game_state_key_event,
+                    (rebind_key_system, menu_focus_system, button_click_system, focus_highlight_system)
+                        .chain(),
+                    window_focus_change_system,
+                    pause_countdown_system.run_if(in_state(InGameState::Paused)),
+                    prepare_system.run_if(is_not_pause_state.and_then(in_state(InGameState::Preparing))),
+                    dying_system.run_if(in_state(InGameState::Dying)),
+                    update_button_labels_system,
+                    apply_settings_system,
+                    (select_theme_system, apply_theme_system).chain(),
+                    fit_layout_system,
+                    // the key taken by a rebind must not toggle fullscreen too.
+                    toggle_fullscreen_system.after(rebind_key_system),
+                    snake_sprite_system,
+                    // the time up must be recorded before the game over menu opens in the next frame.
+                    record_game_over_system.after(time_attack_system),
+                    run_stats_system.run_if(is_not_pause_state.and_then(in_state(InGameState::Playing))),
+                    (food_expiry_system, food_respawn_system)
+                        .chain()
+                        .run_if(is_not_pause_state.and_then(in_state(InGameState::Playing))),
+                    (hazard_move_system, hazard_hit_system)
+                        .chain()
+                        .run_if(
+                            is_not_pause_state
+                                .and_then(is_not_round_ending)
+                                .and_then(in_state(InGameState::Playing)),
+                        ),
+                    (time_attack_system, survival_system)
+                        .run_if(
+                            is_not_pause_state
+                                .and_then(is_not_round_ending)
+                                .and_then(in_state(InGameState::Playing)),
+                        ),
+                    (
+                        food_eaten_juice_system,
+                        game_over_juice_system,
+                        particle_system,
+                        pulse_system,
+                        scatter_system,
+                        screen_shake_system,
+                    ),
+                ),
+            )
+            .insert_resource(Time::<Fixed>::from_seconds(NORMAL_MODE_FIXED_TIMESTEP))
+            .add_event::<bevy::window::WindowFocused>();
+
+        #[cfg(not(target_arch = "wasm32"))] {
+            // Esc cancels a rebinding on the settings screens, it only quits outside of them.
+            app.add_systems(Update, bevy::window::close_on_esc.run_if(in_state(SettingsState::Closed)));
+        }
+
+        #[cfg(feature = "audio")] {
+            app.add_plugins(audio::SoundPlugin);
+        }
+        
+    }
+}
@@ -4,31 +4,8 @@ use bevy::{
 };
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use constants::*;
-use data::{
-    states::{AppState, InGameState},
-    PauseStateRes,
-};
-use game::{
-    dismiss_snake_and_food, is_not_pause_state, setup_boundary, setup_camera, setup_snake_and_food,
-    setup_snake_score,
-};
-use interaction::*;
-use menu::{
-    enter_game_over_menu_system, enter_main_menu_system, exit_game_over_menu_system,
-    exit_main_menu_system,
-};
-use update::{should_eat_food, snake_move};
-
-mod constants;
-mod data;
-mod game;
-mod interaction;
-mod menu;
-mod update;
-
-pub(crate) struct SnakePlugin;
-
+use snake::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use snake::SnakePlugin;
 
 fn main() {
     App::new()
@@ -37,7 +14,9 @@ fn main() {
                 title: "Snake".to_string(),
                 resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT),
                 present_mode: PresentMode::AutoVsync,
-                resizable: false,
+                resizable: true,
+                // in the browser the canvas takes the size of the page, see fit_layout_system.
+                fit_canvas_to_parent: cfg!(target_arch = "wasm32"),
                 ..default()
             }),
             ..default()
@@ -46,53 +25,3 @@ fn main() {
         .add_plugins(SnakePlugin {})
         .run();
 }
-
-impl Plugin for SnakePlugin {
-    fn build(&self, app: &mut bevy::prelude::App) {
-        let background_color: Color = Color::hex("737376").unwrap();
-        app.add_state::<AppState>()
-            .add_state::<InGameState>()
-            .insert_resource(PauseStateRes::new(false, false))
-            .insert_resource(ClearColor(background_color))
-            .add_systems(Startup, setup_camera)
-            .add_systems(OnEnter(AppState::InGame), (setup_boundary,))
-            .add_systems(OnEnter(InGameState::GameOver), enter_game_over_menu_system)
-            .add_systems(OnExit(InGameState::GameOver), exit_game_over_menu_system)
-            .add_systems(
-                OnEnter(InGameState::Playing),
-                (
-                    dismiss_snake_and_food,
-                    apply_deferred,
-                    setup_snake_score,
-                    setup_snake_and_food,
-                )
-                    .chain(),
-            )
-            .add_systems(OnEnter(AppState::MainMenu), enter_main_menu_system)
-            .add_systems(OnExit(AppState::MainMenu), exit_main_menu_system)
-            .add_systems(
-                FixedUpdate,
-                (should_eat_food, apply_deferred, snake_move)
-                    .chain()
-                    .run_if(is_not_pause_state.and_then(
-                        in_state(AppState::InGame).and_then(in_state(InGameState::Playing)),
-                    )),
-            )
-            .add_systems(
-                Update,
-                (
-                    change_direction_key_event,
-                    game_state_key_event,
-                    button_click_system,
-                    window_focus_change_system,
-                ),
-            )
-            .insert_resource(Time::<Fixed>::from_seconds(NORMAL_MODE_FIXED_TIMESTEP))
-            .add_event::<bevy::window::WindowFocused>();
-
-        #[cfg(not(target_arch = "wasm32"))] {
-            app.add_systems(Update, bevy::window::close_on_esc);
-        }
-        
-    }
-}
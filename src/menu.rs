@@ -1,43 +1,90 @@
 use bevy::prelude::*;
-use bevy::window::Window;
-use crate::data::component::{OnMainMenuScreen, MenuButtonAction};
+use crate::constants::START_COUNTDOWN_SECONDS;
+use crate::data::component::{
+    MenuButtonAction, OnGameOverScreen, OnMainMenuScreen, OnPauseScreen, OnPrepareScreen,
+    OnKeyBindingsScreen, OnSettingsScreen, PauseCountdownText, PrepareText, SnakeNode, ThemeColor,
+};
+use crate::data::states::InGameState;
+use crate::data::{
+    Countdown, DeathCause, Direction, PauseStateRes, PrepareMode, RoundMode, RunStats, SnakeType,
+};
+use crate::input_map::{key_name, InputAction, InputMap, Rebinding};
+use crate::records::Records;
+use crate::menu_builder::{spawn_menu, ButtonSize, Menu, MenuLayer, MenuTextTag};
+use crate::settings::{SettingItem, Settings, KEY_BINDINGS_HOTKEY};
+use crate::theme::Theme;
 
 
 pub fn enter_game_over_menu_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    windows: Query<&mut Window>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    run_stats: Res<RunStats>,
+    records: Res<Records>,
+    round: Res<RoundMode>,
 ) {
-    let window = windows.single();
-    let window_width = window.resolution.width();
-    let node_width: f32 = 500.;
-    let node_height: f32 = 500.;
-
-    let padding_left = (window_width - node_width) / 2.0;
-
-    let title_color = Color::rgba_u8(117, 15, 127, 180);
-    spawn_menu(
-        &mut commands,
-        asset_server,
-        padding_left,
-        node_width,
-        node_height,
-        title_color,
-        "Game Over",
-    );
+    let cause = run_stats.cause.map(|cause| cause.describe()).unwrap_or("");
+    let title = match run_stats.cause {
+        Some(DeathCause::TimeUp) => "Time's Up",
+        Some(DeathCause::Ended) => "Round Over",
+        _ => "Game Over",
+    };
+    let best = if run_stats.new_best {
+        "New personal best!".to_string()
+    } else {
+        format!("Personal best: {}", records.best(round.mode))
+    };
+    let table = records.table(round.mode).iter().map(|score| score.to_string()).collect::<Vec<_>>();
+    let table = if table.is_empty() { "-".to_string() } else { table.join("  ") };
+    let menu = Menu::new(title, ThemeColor::GameOverTitle)
+        .text(
+            format!("Mode: {}    Self collision: {}", round.mode.name(), round.self_collision.name()),
+            24.,
+            None,
+        )
+        .text(cause.to_string(), 26., None)
+        .text(
+            format!(
+                "Score: {}    Length: {}    Food: {}",
+                run_stats.score, run_stats.length, run_stats.food_eaten
+            ),
+            24.,
+            None,
+        )
+        .text(
+            format!("Time: {:.1}s", run_stats.time_survived),
+            24.,
+            None,
+        )
+        .text(best, 24., None)
+        .text(format!("High scores: {}", table), 24., None)
+        // lower buttons, the six of them fit under the stats.
+        .button_size(ButtonSize::Medium)
+        .buttons([
+        MenuButtonAction::StartDefaultGame,
+        MenuButtonAction::StartGameLevel1,
+        MenuButtonAction::StartGameLevel2,
+        MenuButtonAction::StartGameLevel3,
+        MenuButtonAction::MainMenu,
+        MenuButtonAction::Quit,
+    ]);
+    spawn_menu(&mut commands, &asset_server, &settings, &theme, menu, OnGameOverScreen);
 }
 
+// the game over menu and the main menu can be both alive in the frame we go back to the main menu,
+// so each of them has its own marker.
 pub fn exit_game_over_menu_system(
     mut commands: Commands,
-    query: Query<Entity, With<OnMainMenuScreen>>,
+    query: Query<Entity, With<OnGameOverScreen>>,
 ) {
-    if let Ok(entity) = query.get_single() {
+    for entity in &query {
         commands.entity(entity).despawn_recursive();
     }
 }
 
 pub fn exit_main_menu_system(mut commands: Commands, query: Query<Entity, With<OnMainMenuScreen>>) {
-    if let Ok(entity) = query.get_single() {
+    for entity in &query {
         commands.entity(entity).despawn_recursive();
     }
 }
@@ -45,192 +92,295 @@ pub fn exit_main_menu_system(mut commands: Commands, query: Query<Entity, With<O
 pub fn enter_main_menu_system(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    windows: Query<&mut Window>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
 ) {
-    let window = windows.single();
-    let window_width = window.resolution.width();
-    let node_width: f32 = 500.;
-    let node_height: f32 = 500.;
-
-    let padding_left = (window_width - node_width) / 2.0;
-
-    let title_color = Color::rgba_u8(147, 215, 197, 255);
-    spawn_menu(
-        &mut commands,
-        asset_server,
-        padding_left,
-        node_width,
-        node_height,
-        title_color,
-        "Snake",
-    );
-}
-
-pub fn spawn_menu(
-    commands: &mut Commands,
+    // wide enough for the name of the mode.
+    let menu = Menu::new("Snake", ThemeColor::Title).button_size(ButtonSize::Medium).buttons([
+        MenuButtonAction::CycleGameMode,
+        MenuButtonAction::StartDefaultGame,
+        MenuButtonAction::StartGameLevel1,
+        MenuButtonAction::StartGameLevel2,
+        MenuButtonAction::StartGameLevel3,
+        MenuButtonAction::OpenSettings,
+        MenuButtonAction::Quit,
+    ]);
+    spawn_menu(&mut commands, &asset_server, &settings, &theme, menu, OnMainMenuScreen);
+}
+
+pub fn enter_pause_menu_system(
+    mut commands: Commands,
     asset_server: Res<AssetServer>,
-    padding_left: f32,
-    node_width: f32,
-    node_height: f32,
-    title_color: Color,
-    title_content: &str,
+    pause_state: Res<PauseStateRes>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    // a translucent layer over the whole board, the menu sits in its center.
+    let menu = Menu::new("Paused", ThemeColor::Title)
+        .layer(MenuLayer::Overlay)
+        .text(
+            pause_reason(&pause_state, &settings.key_bindings),
+            26.,
+            Some(MenuTextTag::PauseCountdown),
+        )
+        .buttons([
+            MenuButtonAction::Resume,
+            MenuButtonAction::Restart,
+            MenuButtonAction::EndRound,
+            MenuButtonAction::OpenSettings,
+            MenuButtonAction::MainMenu,
+        ]);
+    spawn_menu(&mut commands, &asset_server, &settings, &theme, menu, OnPauseScreen);
+}
+
+pub fn exit_pause_menu_system(mut commands: Commands, query: Query<Entity, With<OnPauseScreen>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// ticks the countdown started by the resume action, then goes back to playing.
+pub fn pause_countdown_system(
+    time: Res<Time>,
+    mut countdown: ResMut<Countdown>,
+    mut pause_state: ResMut<PauseStateRes>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut text_query: Query<&mut Text, With<PauseCountdownText>>,
+    settings: Res<Settings>,
 ) {
+    if countdown.is_running() && countdown.timer.tick(time.delta()).just_finished() {
+        countdown.remaining -= 1;
+        if !countdown.is_running() {
+            pause_state.clear();
+            game_state.set(InGameState::Playing);
+            return;
+        }
+    }
+
+    let content = if countdown.is_running() {
+        format!("Resuming in {}", countdown.remaining)
+    } else {
+        pause_reason(&pause_state, &settings.key_bindings)
+    };
+    for mut text in &mut text_query {
+        if text.sections[0].value != content {
+            text.sections[0].value = content.clone();
+        }
+    }
+}
+
+pub fn enter_prepare_screen_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+    mut countdown: ResMut<Countdown>,
+) {
+    let content = match settings.prepare_mode {
+        PrepareMode::Countdown => {
+            countdown.start(START_COUNTDOWN_SECONDS);
+            START_COUNTDOWN_SECONDS.to_string()
+        }
+        PrepareMode::WaitForKey => {
+            countdown.cancel();
+            "Press a direction key to start".to_string()
+        }
+    };
+
     commands
         .spawn((
             NodeBundle {
                 style: Style {
-                    width: Val::Px(node_width),
-                    height: Val::Px(node_height),
-                    left: Val::Px(padding_left),
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
                     align_items: AlignItems::Center,
-                    align_self: AlignSelf::Center,
                     justify_content: JustifyContent::Center,
                     position_type: PositionType::Absolute,
                     ..default()
                 },
+                z_index: ZIndex::Global(10),
                 ..default()
             },
-            OnMainMenuScreen,
+            OnPrepareScreen,
         ))
         .with_children(|parent| {
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        width: Val::Px(node_width),
-                        height: Val::Px(node_height),
-                        flex_direction: FlexDirection::Column,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        row_gap: Val::Px(6.),
-                        ..default()
+            parent.spawn((
+                TextBundle::from_section(
+                    content,
+                    TextStyle {
+                        font: asset_server.load(&theme.font),
+                        font_size: 60.0,
+                        color: theme.title,
                     },
-                    background_color: Color::rgba_u8(41, 147, 134, 200).into(),
-                    ..default()
-                })
-                .with_children(|parent| {
-                    // 标题
-                    parent.spawn(
-                        TextBundle::from_section(
-                            title_content,
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                font_size: 60.0,
-                                color: title_color,
-                            },
-                        )
-                        .with_style(Style {
-                            margin: UiRect::all(Val::Px(20.0)),
-                            ..default()
-                        }),
-                    );
-
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(200.0),
-                                    height: Val::Px(50.0),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::StartGameLevel1,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "(E) Easy",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
-                                    color: Color::rgba_u8(136, 190, 200, 187),
-                                },
-                            ));
-                        });
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(200.0),
-                                    height: Val::Px(50.0),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::StartGameLevel2,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "(N) Normal",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
-                                    color: Color::rgba_u8(136, 190, 200, 187),
-                                },
-                            ));
-                        });
-
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(200.0),
-                                    height: Val::Px(50.0),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::StartGameLevel3,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "(H) Hard",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
-                                    color: Color::rgba_u8(136, 190, 200, 187),
-                                },
-                            ));
-                        });
-
-                    // 退出按钮
-                    parent
-                        .spawn((
-                            ButtonBundle {
-                                style: Style {
-                                    width: Val::Px(200.0),
-                                    height: Val::Px(50.0),
-                                    margin: UiRect::all(Val::Px(10.0)),
-                                    justify_content: JustifyContent::Center,
-                                    align_items: AlignItems::Center,
-                                    ..default()
-                                },
-                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
-                                ..default()
-                            },
-                            MenuButtonAction::Quit,
-                        ))
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "(Q) Quit",
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 30.0,
-                                    color: Color::rgba_u8(136, 190, 200, 187),
-                                },
-                            ));
-                        });
-                });
+                ),
+                PrepareText,
+                ThemeColor::Title,
+            ));
         });
-}
\ No newline at end of file
+}
+
+pub fn exit_prepare_screen_system(mut commands: Commands, query: Query<Entity, With<OnPrepareScreen>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// waits for the countdown or for the first direction key, then the snake starts moving.
+pub fn prepare_system(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut countdown: ResMut<Countdown>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut snake_query: Query<&mut SnakeNode>,
+    mut text_query: Query<&mut Text, With<PrepareText>>,
+) {
+    match settings.prepare_mode {
+        PrepareMode::Countdown => {
+            if countdown.timer.tick(time.delta()).just_finished() {
+                countdown.remaining -= 1;
+            }
+            if !countdown.is_running() {
+                // nobody chose a direction during the countdown, keep the old default.
+                for mut node in &mut snake_query {
+                    if node.snake_type == SnakeType::Unknown {
+                        node.snake_type = SnakeType::Head(Direction::Down);
+                    }
+                }
+                game_state.set(InGameState::Playing);
+                return;
+            }
+            let content = countdown.remaining.to_string();
+            for mut text in &mut text_query {
+                if text.sections[0].value != content {
+                    text.sections[0].value = content.clone();
+                }
+            }
+        }
+        PrepareMode::WaitForKey => {
+            if snake_query
+                .iter()
+                .any(|node| matches!(node.snake_type, SnakeType::Head(_)))
+            {
+                game_state.set(InGameState::Playing);
+            }
+        }
+    }
+}
+
+pub fn enter_settings_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    // low buttons like the key bindings, all the rows fit in the window.
+    let menu = Menu::sub_menu("Settings", MenuButtonAction::CloseSettings)
+        .title_size(40.)
+        .button_size(ButtonSize::Small)
+        .buttons(SettingItem::ALL.map(MenuButtonAction::CycleSetting))
+        .button(MenuButtonAction::OpenKeyBindings);
+    spawn_menu(&mut commands, &asset_server, &settings, &theme, menu, OnSettingsScreen);
+}
+
+pub fn exit_settings_menu_system(mut commands: Commands, query: Query<Entity, With<OnSettingsScreen>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn enter_key_bindings_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    let menu = Menu::sub_menu("Key Bindings", MenuButtonAction::CloseKeyBindings)
+        .title_size(40.)
+        .button_size(ButtonSize::Small)
+        .buttons(InputAction::ALL.map(MenuButtonAction::Rebind))
+        .button(MenuButtonAction::ResetKeyBindings);
+    spawn_menu(&mut commands, &asset_server, &settings, &theme, menu, OnKeyBindingsScreen);
+}
+
+pub fn exit_key_bindings_menu_system(
+    mut commands: Commands,
+    query: Query<Entity, With<OnKeyBindingsScreen>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// the text of a menu button, the key of its action is shown in front of it.
+pub fn button_label(
+    action: MenuButtonAction,
+    settings: &Settings,
+    rebinding: Option<InputAction>,
+) -> String {
+    let input_map = &settings.key_bindings;
+    let hint_label = |input_action: InputAction, name: &str| {
+        format!("({}) {}", input_map.hint(input_action), name)
+    };
+    return match action {
+        MenuButtonAction::StartGameLevel1 => hint_label(InputAction::StartEasy, "Easy"),
+        MenuButtonAction::StartGameLevel2 => hint_label(InputAction::StartNormal, "Normal"),
+        MenuButtonAction::StartGameLevel3 => hint_label(InputAction::StartHard, "Hard"),
+        // the difficulty chosen in the settings.
+        MenuButtonAction::StartDefaultGame => {
+            hint_label(InputAction::StartDefault, &format!("Play ({:?})", settings.difficulty))
+        }
+        MenuButtonAction::CycleGameMode => {
+            hint_label(InputAction::GameMode, &format!("Mode: {}", settings.game_mode.name()))
+        }
+        MenuButtonAction::EndRound => hint_label(InputAction::EndRound, "End Round"),
+        MenuButtonAction::Resume => hint_label(InputAction::Pause, "Resume"),
+        MenuButtonAction::Restart => hint_label(InputAction::Restart, "Restart"),
+        MenuButtonAction::MainMenu => hint_label(InputAction::MainMenu, "Main Menu"),
+        MenuButtonAction::OpenSettings => hint_label(InputAction::Settings, "Settings"),
+        MenuButtonAction::CloseSettings => hint_label(InputAction::Back, "Back"),
+        MenuButtonAction::CycleSetting(item) => settings.label(item),
+        MenuButtonAction::OpenKeyBindings => {
+            format!("({}) Key Bindings", key_name(KEY_BINDINGS_HOTKEY))
+        }
+        MenuButtonAction::CloseKeyBindings => hint_label(InputAction::Back, "Back"),
+        MenuButtonAction::Rebind(input_action) => {
+            if rebinding == Some(input_action) {
+                format!("{}: press a key, Esc cancels", input_action.name())
+            } else {
+                format!("{}: {}", input_action.name(), input_map.describe(input_action))
+            }
+        }
+        MenuButtonAction::ResetKeyBindings => "Reset to Defaults".to_string(),
+        MenuButtonAction::Quit => hint_label(InputAction::Quit, "Quit"),
+    };
+}
+
+// keeps the labels of the menu buttons in sync with the settings and the key bindings.
+pub fn update_button_labels_system(
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+    button_query: Query<(&MenuButtonAction, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if !settings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (action, children) in &button_query {
+        let label = button_label(*action, &settings, rebinding.0);
+        for &child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.clone();
+                }
+            }
+        }
+    }
+}
+
+// the pause of the player wins, the game does not resume on its own when the window gets the focus back.
+fn pause_reason(pause_state: &PauseStateRes, input_map: &InputMap) -> String {
+    if pause_state.lose_focus_pause && !pause_state.user_click_pause {
+        return "The window lost focus".to_string();
+    }
+    return format!("Press {} to resume", input_map.hint(InputAction::Pause));
+}
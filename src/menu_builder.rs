@@ -0,0 +1,267 @@
+use bevy::prelude::*;
+
+use crate::data::component::{MenuButtonAction, PauseCountdownText, ThemeColor};
+use crate::menu::button_label;
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+const PANEL_WIDTH: f32 = 500.;
+
+// How a menu sits over the rest of the window.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MenuLayer {
+    Screen,  // the only thing on the window, like the main menu.
+    Overlay, // darkens the board under it, like the pause menu.
+    Modal,   // covers the other menus, like the settings.
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ButtonSize {
+    Large,
+    Medium,
+    Small, // for the screens with many rows.
+}
+
+impl ButtonSize {
+    // the size, the margin and the font size of the button.
+    fn dimensions(&self) -> (Vec2, f32, f32) {
+        return match self {
+            ButtonSize::Large => (Vec2::new(200., 50.), 10., 30.),
+            ButtonSize::Medium => (Vec2::new(380., 40.), 4., 26.),
+            ButtonSize::Small => (Vec2::new(380., 30.), 2., 22.),
+        };
+    }
+}
+
+// Marks a text of a menu that a system changes later.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MenuTextTag {
+    PauseCountdown,
+}
+
+pub enum MenuItem {
+    // the label and the hotkey in front of it come from button_label, so they follow the key bindings.
+    Button(MenuButtonAction),
+    Text {
+        content: String,
+        font_size: f32,
+        tag: Option<MenuTextTag>,
+    },
+}
+
+// A menu screen described as data, spawn_menu builds its ui. the clicks and the hotkeys of the
+// buttons are routed through their MenuButtonAction by button_click_system.
+pub struct Menu {
+    title: String,
+    title_color: ThemeColor,
+    title_size: f32,
+    layer: MenuLayer,
+    button_size: ButtonSize,
+    items: Vec<MenuItem>,
+    back: Option<MenuButtonAction>,
+}
+
+impl Menu {
+    pub fn new(title: &str, title_color: ThemeColor) -> Self {
+        return Menu {
+            title: title.to_string(),
+            title_color,
+            title_size: 60.,
+            layer: MenuLayer::Screen,
+            button_size: ButtonSize::Large,
+            items: Vec::new(),
+            back: None,
+        };
+    }
+
+    // a menu opened from another one, it covers its parent and always ends with the back button.
+    pub fn sub_menu(title: &str, back: MenuButtonAction) -> Self {
+        let mut menu = Menu::new(title, ThemeColor::Title).layer(MenuLayer::Modal);
+        menu.back = Some(back);
+        return menu;
+    }
+
+    pub fn layer(mut self, layer: MenuLayer) -> Self {
+        self.layer = layer;
+        return self;
+    }
+
+    pub fn title_size(mut self, title_size: f32) -> Self {
+        self.title_size = title_size;
+        return self;
+    }
+
+    pub fn button_size(mut self, button_size: ButtonSize) -> Self {
+        self.button_size = button_size;
+        return self;
+    }
+
+    pub fn button(mut self, action: MenuButtonAction) -> Self {
+        self.items.push(MenuItem::Button(action));
+        return self;
+    }
+
+    pub fn buttons(mut self, actions: impl IntoIterator<Item = MenuButtonAction>) -> Self {
+        self.items.extend(actions.into_iter().map(MenuItem::Button));
+        return self;
+    }
+
+    pub fn text(mut self, content: String, font_size: f32, tag: Option<MenuTextTag>) -> Self {
+        self.items.push(MenuItem::Text { content, font_size, tag });
+        return self;
+    }
+}
+
+pub fn spawn_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    settings: &Settings,
+    theme: &Theme,
+    menu: Menu,
+    marker: impl Component,
+) {
+    let (backdrop, z_index, panel) = match menu.layer {
+        MenuLayer::Screen => (None, ZIndex::default(), ThemeColor::Panel),
+        MenuLayer::Overlay => (Some(ThemeColor::Backdrop), ZIndex::Global(10), ThemeColor::Panel),
+        MenuLayer::Modal => (None, ZIndex::Global(20), ThemeColor::ModalPanel),
+    };
+    let button_size = menu.button_size;
+    let items = menu.items.into_iter().chain(menu.back.map(MenuItem::Button));
+
+    // centered in the window whatever its size, see fit_layout_system.
+    let mut root = commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            background_color: backdrop.map_or(Color::NONE, |slot| theme.color(slot)).into(),
+            z_index,
+            ..default()
+        },
+        marker,
+    ));
+    if let Some(slot) = backdrop {
+        root.insert(slot);
+    }
+    root
+        .with_children(|parent| {
+            parent
+                .spawn((NodeBundle {
+                    style: Style {
+                        width: Val::Px(PANEL_WIDTH),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        padding: UiRect::all(Val::Px(20.)),
+                        row_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    background_color: theme.color(panel).into(),
+                    ..default()
+                }, panel))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            menu.title,
+                            TextStyle {
+                                font: asset_server.load(&theme.font),
+                                font_size: menu.title_size,
+                                color: theme.color(menu.title_color),
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::bottom(Val::Px(10.0)),
+                            ..default()
+                        }),
+                        menu.title_color,
+                    ));
+                    for item in items {
+                        match item {
+                            MenuItem::Button(action) => {
+                                let label = button_label(action, settings, None);
+                                spawn_button(parent, asset_server, theme, &label, action, button_size);
+                            }
+                            MenuItem::Text { content, font_size, tag } => {
+                                spawn_text(parent, asset_server, theme, content, font_size, tag);
+                            }
+                        }
+                    }
+                });
+        });
+}
+
+fn spawn_text(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    theme: &Theme,
+    content: String,
+    font_size: f32,
+    tag: Option<MenuTextTag>,
+) {
+    let mut text = parent.spawn((
+        TextBundle::from_section(
+            content,
+            TextStyle {
+                font: asset_server.load(&theme.font),
+                font_size,
+                color: theme.text,
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::bottom(Val::Px(10.0)),
+            ..default()
+        }),
+        ThemeColor::Text,
+    ));
+    if let Some(tag) = tag {
+        match tag {
+            MenuTextTag::PauseCountdown => text.insert(PauseCountdownText),
+        };
+    }
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    theme: &Theme,
+    label: &str,
+    action: MenuButtonAction,
+    button_size: ButtonSize,
+) {
+    let (size, margin, font_size) = button_size.dimensions();
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(size.x),
+                    height: Val::Px(size.y),
+                    margin: UiRect::all(Val::Px(margin)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                // focus_highlight_system keeps it in the colors of the theme.
+                background_color: theme.button.into(),
+                ..default()
+            },
+            action,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font: asset_server.load(&theme.font),
+                        font_size,
+                        color: theme.text,
+                    },
+                ),
+                ThemeColor::Text,
+            ));
+        });
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::constants::*;
+use crate::data::component::{ModeText, Obstacle, SnakeLength, ThemeColor};
+use crate::data::occupancy::{BoardOccupancy, CellContent};
+use crate::data::snake_body::SnakeBody;
+use crate::data::states::InGameState;
+use crate::data::{Board, DeathCause, GameMode, GameOverEvent, RoundMode, SelfCollisionPolicy};
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+// how often survival looks for a cell far enough from the head before it waits for the next timer.
+const OBSTACLE_TRIES: usize = 8;
+
+// starts the round in the mode chosen in the main menu, a time attack shows its clock under the board.
+pub fn setup_mode_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    board: Res<Board>,
+    theme: Res<Theme>,
+    mut round: ResMut<RoundMode>,
+) {
+    let mode = settings.game_mode;
+    *round = RoundMode {
+        mode,
+        self_collision: if mode.can_die() { settings.self_collision } else { SelfCollisionPolicy::CutAtImpact },
+        time_left: mode.time_limit().unwrap_or(0.),
+        obstacle_timer: Timer::from_seconds(SURVIVAL_OBSTACLE_SECONDS, TimerMode::Repeating),
+    };
+    let Some(limit) = mode.time_limit() else {
+        return;
+    };
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                time_label(limit),
+                TextStyle {
+                    font: asset_server.load(&theme.font),
+                    font_size: 40.0,
+                    color: theme.score,
+                },
+            ),
+            transform: Transform {
+                // just under the board.
+                translation: Vec3::new(0., -(board.height() / 2. + 35.), 0.),
+                ..default()
+            },
+            text_anchor: Anchor::Center,
+            ..default()
+        },
+        ModeText,
+        ThemeColor::Score,
+    ));
+}
+
+fn time_label(time_left: f32) -> String {
+    return format!("Time: {}", time_left.ceil().max(0.) as u32);
+}
+
+pub fn dismiss_mode(mut commands: Commands, query: Query<Entity, Or<(With<ModeText>, With<Obstacle>)>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// counts down a time attack, the round is over without a death animation when the time is up.
+pub fn time_attack_system(
+    time: Res<Time>,
+    snake_body: Res<SnakeBody>,
+    mut round: ResMut<RoundMode>,
+    score_query: Query<&SnakeLength>,
+    mut text_query: Query<&mut Text, With<ModeText>>,
+    mut game_state: ResMut<NextState<InGameState>>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+) {
+    if round.mode.time_limit().is_none() {
+        return;
+    }
+    round.time_left -= time.delta_seconds();
+    for mut text in &mut text_query {
+        let style = text.sections.first().unwrap().style.clone();
+        text.sections = vec![TextSection::new(time_label(round.time_left), style)];
+    }
+    if round.time_left > 0. {
+        return;
+    }
+    game_over_events.send(GameOverEvent {
+        cause: DeathCause::TimeUp,
+        score: score_query.get_single().map_or(0, |len| len.0),
+        length: snake_body.len(),
+    });
+    game_state.set(InGameState::GameOver);
+}
+
+// puts up a new wall in a survival round every time the timer finishes, never right in front of the head.
+pub fn survival_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    snake_body: Res<SnakeBody>,
+    mut round: ResMut<RoundMode>,
+    mut occupancy: ResMut<BoardOccupancy>,
+) {
+    if round.mode != GameMode::Survival || !round.obstacle_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some((_, head)) = snake_body.head() else {
+        return;
+    };
+    let cell = (0..OBSTACLE_TRIES)
+        .filter_map(|_| occupancy.random_free_cell())
+        .find(|cell| {
+            let distance = (*cell - head).abs();
+            return distance.x + distance.y > SURVIVAL_OBSTACLE_DISTANCE;
+        });
+    let Some(cell) = cell else {
+        return;
+    };
+    occupancy.set(cell, CellContent::Wall);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: theme.board_border,
+                custom_size: Some(Vec2::splat(SNAKE_NODE_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(Board::cell_position(cell).extend(0.)),
+            ..default()
+        },
+        Obstacle,
+        ThemeColor::BoardBorder,
+    ));
+}
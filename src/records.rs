@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::HIGH_SCORE_COUNT;
+use crate::data::component::SnakeLength;
+use crate::data::snake_body::SnakeBody;
+use crate::data::{DeathCause, GameMode, GameOverEvent, RoundMode, RunStats};
+use crate::storage;
+
+// The personal bests of the player, they are kept next to the settings.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Records {
+    // the best of the files written before there were modes, it is moved to classic on load.
+    #[serde(skip_serializing)]
+    pub best_score: usize,
+    // the best scores of every mode, the highest first.
+    pub high_scores: BTreeMap<GameMode, Vec<usize>>,
+}
+
+impl Records {
+    pub fn best(&self, mode: GameMode) -> usize {
+        return self.table(mode).first().copied().unwrap_or(0);
+    }
+
+    pub fn table(&self, mode: GameMode) -> &[usize] {
+        return self.high_scores.get(&mode).map_or(&[], |scores| scores.as_slice());
+    }
+
+    // puts the score in the table of the mode, true when it made it in.
+    pub fn add(&mut self, mode: GameMode, score: usize) -> bool {
+        if score == 0 {
+            return false;
+        }
+        let scores = self.high_scores.entry(mode).or_default();
+        let index = scores.partition_point(|&best| best >= score);
+        if index >= HIGH_SCORE_COUNT {
+            return false;
+        }
+        scores.insert(index, score);
+        scores.truncate(HIGH_SCORE_COUNT);
+        return true;
+    }
+}
+
+pub fn load_records_system(mut records: ResMut<Records>) {
+    let Some(content) = storage::read("records") else {
+        return;
+    };
+    if let Some(loaded) = parse_records(&content) {
+        *records = loaded;
+    }
+}
+
+fn parse_records(content: &str) -> Option<Records> {
+    match ron::from_str::<Records>(content) {
+        Ok(mut loaded) => {
+            let legacy = std::mem::take(&mut loaded.best_score);
+            if legacy > loaded.best(GameMode::Classic) {
+                loaded.add(GameMode::Classic, legacy);
+            }
+            return Some(loaded);
+        }
+        Err(err) => {
+            warn!("failed to parse the records: {}", err);
+            return None;
+        }
+    }
+}
+
+pub fn save_records(records: &Records) {
+    match ron::ser::to_string_pretty(records, PrettyConfig::default()) {
+        Ok(content) => storage::write("records", &content),
+        Err(err) => warn!("failed to serialize the records: {}", err),
+    }
+}
+
+pub fn reset_run_stats_system(mut run_stats: ResMut<RunStats>) {
+    *run_stats = RunStats::default();
+}
+
+// counts the playing time of the round.
+pub fn run_stats_system(time: Res<Time>, mut run_stats: ResMut<RunStats>) {
+    run_stats.time_survived += time.delta_seconds();
+}
+
+// keeps what ended the round and saves a new personal best.
+pub fn record_game_over_system(
+    mut events: EventReader<GameOverEvent>,
+    round: Res<RoundMode>,
+    mut run_stats: ResMut<RunStats>,
+    mut records: ResMut<Records>,
+) {
+    for event in events.read() {
+        // the fixed update can run twice in a frame, only the first death counts.
+        if run_stats.cause.is_some() {
+            continue;
+        }
+        record(&mut run_stats, &mut records, round.mode, event);
+    }
+}
+
+// a round ended from the pause menu has no death, it is recorded as it stands.
+pub fn record_ended_round_system(
+    round: Res<RoundMode>,
+    snake_body: Res<SnakeBody>,
+    score_query: Query<&SnakeLength>,
+    mut run_stats: ResMut<RunStats>,
+    mut records: ResMut<Records>,
+) {
+    if run_stats.cause.is_some() {
+        return;
+    }
+    let event = GameOverEvent {
+        cause: DeathCause::Ended,
+        score: score_query.get_single().map_or(0, |len| len.0),
+        length: snake_body.len(),
+    };
+    record(&mut run_stats, &mut records, round.mode, &event);
+}
+
+fn record(run_stats: &mut RunStats, records: &mut Records, mode: GameMode, event: &GameOverEvent) {
+    run_stats.cause = Some(event.cause);
+    run_stats.score = event.score;
+    run_stats.length = event.length;
+    run_stats.new_best = event.score > records.best(mode);
+    if records.add(mode, event.score) {
+        save_records(records);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_table_keeps_the_best_scores() {
+        let mut records = Records::default();
+        for score in 1..=HIGH_SCORE_COUNT + 2 {
+            assert!(records.add(GameMode::Classic, score));
+        }
+        assert!(!records.add(GameMode::Classic, 1));
+        let best: Vec<usize> = (3..=HIGH_SCORE_COUNT + 2).rev().collect();
+        assert_eq!(records.table(GameMode::Classic), best.as_slice());
+        assert_eq!(records.best(GameMode::Classic), HIGH_SCORE_COUNT + 2);
+        assert!(records.table(GameMode::Zen).is_empty());
+    }
+
+    #[test]
+    fn a_score_of_zero_is_not_kept() {
+        let mut records = Records::default();
+        assert!(!records.add(GameMode::Survival, 0));
+        assert!(records.table(GameMode::Survival).is_empty());
+    }
+
+    #[test]
+    fn the_best_of_an_old_file_goes_to_classic() {
+        let records = parse_records("(best_score: 42)").unwrap();
+        assert_eq!(records.table(GameMode::Classic), &[42]);
+        assert_eq!(records.best_score, 0);
+        assert_eq!(records.best(GameMode::Zen), 0);
+    }
+
+    #[test]
+    fn the_tables_are_saved_and_loaded() {
+        let mut records = Records::default();
+        records.add(GameMode::TimeAttack60, 12);
+        records.add(GameMode::Classic, 7);
+        let content = ron::to_string(&records).unwrap();
+        assert!(!content.contains("best_score"));
+        assert_eq!(parse_records(&content), Some(records));
+    }
+}
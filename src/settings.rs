@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::data::{Difficulty, GameMode, HazardLevel, PrepareMode, SelfCollisionPolicy};
+use crate::input_map::{key_name, InputMap};
+use crate::storage;
+
+// bump it when a field changes its meaning, files of other versions are replaced by the defaults.
+pub const SETTINGS_VERSION: u32 = 1;
+
+// opens the key bindings screen, it is the row after the settings.
+pub const KEY_BINDINGS_HOTKEY: KeyCode = KeyCode::K;
+
+// name and asset path of the built-in themes, see theme.rs.
+pub const THEMES: [(&str, &str); 5] = [
+    ("Classic", "themes/classic.theme.ron"),
+    ("Dark", "themes/dark.theme.ron"),
+    ("Light", "themes/light.theme.ron"),
+    ("High Contrast", "themes/high_contrast.theme.ron"),
+    ("Colorblind Safe", "themes/colorblind_safe.theme.ron"),
+];
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub difficulty: Difficulty,
+    pub game_mode: GameMode, // chosen in the main menu, not on the settings screen.
+    pub board_size: i32,
+    pub wrap: bool,
+    pub portals: bool, // two pairs of portals on the board.
+    pub hazards: HazardLevel,
+    pub food_count: usize, // the food on the board at once.
+    pub food_expires: bool, // the food goes when it is not eaten in time.
+    pub self_collision: SelfCollisionPolicy,
+    pub prepare_mode: PrepareMode,
+    pub show_grid: bool,
+    pub theme: String,
+    pub volume: f32,
+    pub muted: bool, // silences the game but keeps the volume for later.
+    pub touch_dpad: bool, // the on-screen D-pad for touch screens, swipes work without it.
+    pub effects: bool,    // particles, pulses and screen shake, off for players sensitive to motion.
+    pub key_bindings: InputMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        return Settings {
+            version: SETTINGS_VERSION,
+            difficulty: Difficulty::Normal,
+            game_mode: GameMode::Classic,
+            board_size: DEFAULT_BOARD_SIZE,
+            wrap: false,
+            portals: false,
+            hazards: HazardLevel::Off,
+            food_count: 1,
+            food_expires: false,
+            self_collision: SelfCollisionPolicy::Die,
+            prepare_mode: PrepareMode::Countdown,
+            show_grid: true,
+            theme: THEMES[0].0.to_string(),
+            volume: 1.0,
+            muted: false,
+            touch_dpad: false,
+            effects: true,
+            key_bindings: InputMap::default(),
+        };
+    }
+}
+
+// One row of the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingItem {
+    Difficulty,
+    BoardSize,
+    Wrap,
+    PrepareMode,
+    Grid,
+    Theme,
+    Volume,
+    Mute,
+    TouchDpad,
+    Effects,
+    Portals,
+    Hazards,
+    FoodCount,
+    FoodExpiry,
+    SelfCollision,
+}
+
+impl SettingItem {
+    pub const ALL: [SettingItem; 15] = [
+        SettingItem::Difficulty,
+        SettingItem::BoardSize,
+        SettingItem::Wrap,
+        SettingItem::PrepareMode,
+        SettingItem::Grid,
+        SettingItem::Theme,
+        SettingItem::Volume,
+        SettingItem::Mute,
+        SettingItem::TouchDpad,
+        SettingItem::Effects,
+        SettingItem::Portals,
+        SettingItem::Hazards,
+        SettingItem::FoodCount,
+        SettingItem::FoodExpiry,
+        SettingItem::SelfCollision,
+    ];
+
+    // the rows are selected with the number keys 1 to 9 and 0, the rows after them with a letter.
+    pub fn hotkey(&self) -> KeyCode {
+        return match self {
+            SettingItem::Difficulty => KeyCode::Key1,
+            SettingItem::BoardSize => KeyCode::Key2,
+            SettingItem::Wrap => KeyCode::Key3,
+            SettingItem::PrepareMode => KeyCode::Key4,
+            SettingItem::Grid => KeyCode::Key5,
+            SettingItem::Theme => KeyCode::Key6,
+            SettingItem::Volume => KeyCode::Key7,
+            SettingItem::Mute => KeyCode::Key8,
+            SettingItem::TouchDpad => KeyCode::Key9,
+            SettingItem::Effects => KeyCode::Key0,
+            SettingItem::Portals => KeyCode::P,
+            SettingItem::Hazards => KeyCode::Z,
+            SettingItem::FoodCount => KeyCode::F,
+            SettingItem::FoodExpiry => KeyCode::X,
+            SettingItem::SelfCollision => KeyCode::C,
+        };
+    }
+}
+
+impl Settings {
+    pub fn label(&self, item: SettingItem) -> String {
+        let value = match item {
+            SettingItem::Difficulty => format!("Difficulty: {:?}", self.difficulty),
+            SettingItem::BoardSize => format!("Board: {0}x{0}", self.board_size),
+            SettingItem::Wrap => {
+                format!("Walls: {}", if self.wrap { "Wrap" } else { "Solid" })
+            }
+            SettingItem::PrepareMode => match self.prepare_mode {
+                PrepareMode::Countdown => "Start: Countdown".to_string(),
+                PrepareMode::WaitForKey => "Start: First Key".to_string(),
+            },
+            SettingItem::Grid => {
+                format!("Grid: {}", if self.show_grid { "Shown" } else { "Hidden" })
+            }
+            SettingItem::Theme => format!("Theme: {}", self.theme),
+            SettingItem::Volume => format!("Volume: {}%", (self.volume * 100.).round()),
+            SettingItem::Mute => format!("Sound: {}", if self.muted { "Muted" } else { "On" }),
+            SettingItem::TouchDpad => {
+                format!("Touch D-pad: {}", if self.touch_dpad { "Shown" } else { "Hidden" })
+            }
+            SettingItem::Effects => {
+                format!("Motion Effects: {}", if self.effects { "On" } else { "Off" })
+            }
+            SettingItem::Portals => {
+                format!("Portals: {}", if self.portals { "On" } else { "Off" })
+            }
+            SettingItem::Hazards => format!("Hazards: {:?}", self.hazards),
+            SettingItem::FoodCount => format!("Food: {}", self.food_count),
+            SettingItem::FoodExpiry => {
+                format!("Food Expires: {}", if self.food_expires { "On" } else { "Off" })
+            }
+            SettingItem::SelfCollision => format!("Self Collision: {}", self.self_collision.name()),
+        };
+        return format!("({}) {}", key_name(item.hotkey()), value);
+    }
+
+    // moves the setting to its next value, the last value goes back to the first one.
+    pub fn cycle(&mut self, item: SettingItem) {
+        match item {
+            SettingItem::Difficulty => {
+                self.difficulty = match self.difficulty {
+                    Difficulty::Easy => Difficulty::Normal,
+                    Difficulty::Normal => Difficulty::Hard,
+                    Difficulty::Hard => Difficulty::Easy,
+                };
+            }
+            SettingItem::BoardSize => {
+                let index = BOARD_SIZES.iter().position(|size| *size == self.board_size);
+                self.board_size = match index {
+                    Some(index) => BOARD_SIZES[(index + 1) % BOARD_SIZES.len()],
+                    None => DEFAULT_BOARD_SIZE,
+                };
+            }
+            SettingItem::Wrap => self.wrap = !self.wrap,
+            SettingItem::PrepareMode => {
+                self.prepare_mode = match self.prepare_mode {
+                    PrepareMode::Countdown => PrepareMode::WaitForKey,
+                    PrepareMode::WaitForKey => PrepareMode::Countdown,
+                };
+            }
+            SettingItem::Grid => self.show_grid = !self.show_grid,
+            SettingItem::Theme => {
+                let index = THEMES.iter().position(|(name, _)| *name == self.theme);
+                self.theme = match index {
+                    Some(index) => THEMES[(index + 1) % THEMES.len()].0.to_string(),
+                    None => THEMES[0].0.to_string(),
+                };
+            }
+            SettingItem::Volume => {
+                // 100% -> 75% -> ... -> 0% -> 100%
+                self.volume = if self.volume <= 0. {
+                    1.
+                } else {
+                    ((self.volume - 0.25) * 4.).round() / 4.
+                };
+            }
+            SettingItem::Mute => self.muted = !self.muted,
+            SettingItem::TouchDpad => self.touch_dpad = !self.touch_dpad,
+            SettingItem::Effects => self.effects = !self.effects,
+            SettingItem::Portals => self.portals = !self.portals,
+            SettingItem::Hazards => {
+                self.hazards = match self.hazards {
+                    HazardLevel::Off => HazardLevel::Calm,
+                    HazardLevel::Calm => HazardLevel::Wild,
+                    HazardLevel::Wild => HazardLevel::Off,
+                };
+            }
+            SettingItem::FoodCount => {
+                let index = FOOD_COUNTS.iter().position(|count| *count == self.food_count);
+                self.food_count = match index {
+                    Some(index) => FOOD_COUNTS[(index + 1) % FOOD_COUNTS.len()],
+                    None => FOOD_COUNTS[0],
+                };
+            }
+            SettingItem::FoodExpiry => self.food_expires = !self.food_expires,
+            SettingItem::SelfCollision => self.self_collision = self.self_collision.next(),
+        }
+    }
+}
+
+// the keys of the settings screen rows, Back is read on that screen too so it can not take them.
+pub fn is_settings_hotkey(key: KeyCode) -> bool {
+    return key == KEY_BINDINGS_HOTKEY || SettingItem::ALL.iter().any(|item| item.hotkey() == key);
+}
+
+impl Settings {
+    // an odd board size from BOARD_SIZES, the volume in 0..=1 and a food count from FOOD_COUNTS.
+    fn sanitize(&mut self) {
+        if !BOARD_SIZES.contains(&self.board_size) {
+            self.board_size = DEFAULT_BOARD_SIZE;
+        }
+        self.volume = if self.volume.is_nan() { 1. } else { self.volume.clamp(0., 1.) };
+        if !FOOD_COUNTS.contains(&self.food_count) {
+            self.food_count = FOOD_COUNTS[0];
+        }
+    }
+}
+
+pub fn load_settings_system(mut settings: ResMut<Settings>) {
+    if let Some(loaded) = load_settings() {
+        *settings = loaded;
+    }
+}
+
+pub fn apply_settings_system(settings: Res<Settings>, mut input_map: ResMut<InputMap>) {
+    if settings.is_changed() {
+        *input_map = settings.key_bindings.clone();
+    }
+}
+
+pub fn load_settings() -> Option<Settings> {
+    return parse_settings(&storage::read("settings")?);
+}
+
+// the file can be edited by hand, the values the game can not use are put back to ones it can.
+fn parse_settings(content: &str) -> Option<Settings> {
+    match ron::from_str::<Settings>(content) {
+        Ok(mut settings) if settings.version == SETTINGS_VERSION => {
+            settings.key_bindings.fill_missing();
+            settings.sanitize();
+            Some(settings)
+        }
+        Ok(settings) => {
+            warn!("ignore the settings of version {}", settings.version);
+            None
+        }
+        Err(err) => {
+            warn!("failed to parse the settings: {}", err);
+            None
+        }
+    }
+}
+
+pub fn save_settings(settings: &Settings) {
+    match ron::ser::to_string_pretty(settings, PrettyConfig::default()) {
+        Ok(content) => storage::write("settings", &content),
+        Err(err) => warn!("failed to serialize the settings: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hand_edited_values_are_put_back() {
+        let content = format!(
+            "(version: {}, board_size: 20, volume: 3.5, food_count: 4, wrap: true)",
+            SETTINGS_VERSION
+        );
+        let settings = parse_settings(&content).unwrap();
+        assert_eq!(settings.board_size, DEFAULT_BOARD_SIZE);
+        assert_eq!(settings.volume, 1.);
+        assert_eq!(settings.food_count, FOOD_COUNTS[0]);
+        assert!(settings.wrap);
+    }
+
+    #[test]
+    fn board_sizes_that_break_the_board_are_put_back() {
+        for size in [0, -5, 12] {
+            let content = format!("(version: {}, board_size: {}, volume: -1.0)", SETTINGS_VERSION, size);
+            let settings = parse_settings(&content).unwrap();
+            assert_eq!(settings.board_size, DEFAULT_BOARD_SIZE);
+            assert_eq!(settings.volume, 0.);
+        }
+    }
+
+    #[test]
+    fn valid_values_are_kept() {
+        let content = format!("(version: {}, board_size: 11, volume: 0.5, food_count: 3)", SETTINGS_VERSION);
+        let settings = parse_settings(&content).unwrap();
+        assert_eq!(settings.board_size, 11);
+        assert_eq!(settings.volume, 0.5);
+        assert_eq!(settings.food_count, 3);
+    }
+
+    #[test]
+    fn the_self_collision_policy_is_kept() {
+        let settings = Settings {
+            self_collision: SelfCollisionPolicy::PassThrough,
+            ..default()
+        };
+        let content = ron::ser::to_string(&settings).unwrap();
+        assert_eq!(parse_settings(&content).unwrap().self_collision, SelfCollisionPolicy::PassThrough);
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+
+use crate::constants::SNAKE_NODE_SIZE;
+use crate::data::component::{SnakeNode, SnakePiece, SnakeQuad};
+use crate::data::snake_body::SnakeBody;
+use crate::data::{Board, Direction, Portals, SnakeType};
+
+// the atlas is a 4x4 grid of 32px tiles.
+const ATLAS_TILE_SIZE: f32 = 32.;
+const ATLAS_COLUMNS: usize = 4;
+const ATLAS_ROWS: usize = 4;
+
+// The texture atlas of the snake pieces, see assets/textures/snake.png.
+#[derive(Resource, Default)]
+pub struct SnakeAtlas {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlas>,
+}
+
+pub fn load_snake_atlas_system(
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut snake_atlas: ResMut<SnakeAtlas>,
+) {
+    let image = asset_server.load("textures/snake.png");
+    let layout = TextureAtlas::from_grid(
+        image.clone(),
+        Vec2::splat(ATLAS_TILE_SIZE),
+        ATLAS_COLUMNS,
+        ATLAS_ROWS,
+        None,
+        None,
+    );
+    snake_atlas.layout = texture_atlases.add(layout);
+    snake_atlas.image = image;
+}
+
+pub fn create_snake_piece_bundle(snake_atlas: &SnakeAtlas) -> SpriteSheetBundle {
+    return SpriteSheetBundle {
+        sprite: TextureAtlasSprite {
+            custom_size: Some(Vec2::splat(SNAKE_NODE_SIZE)),
+            ..default()
+        },
+        // every piece of the snake shares the atlas and the z, so the whole snake is drawn in one batch.
+        texture_atlas: snake_atlas.layout.clone(),
+        // shown by snake_sprite_system once the atlas is loaded.
+        visibility: Visibility::Hidden,
+        ..default()
+    };
+}
+
+// the tile of the head looking to the direction.
+fn head_index(direction: Direction) -> usize {
+    return match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+    };
+}
+
+// the tile of the tail, the direction is the side its neighbour is on.
+fn tail_index(neighbour: Direction) -> usize {
+    return 4 + head_index(neighbour);
+}
+
+// the tile of a body piece joining the two sides.
+fn body_index(a: Direction, b: Direction) -> usize {
+    let vertical = |direction: Direction| matches!(direction, Direction::Up | Direction::Down);
+    if vertical(a) && vertical(b) {
+        return 8;
+    }
+    if !vertical(a) && !vertical(b) {
+        return 9;
+    }
+    let (v, h) = if vertical(a) { (a, b) } else { (b, a) };
+    return match (v, h) {
+        (Direction::Up, Direction::Right) => 10,
+        (Direction::Up, _) => 11,
+        (_, Direction::Right) => 12,
+        _ => 13,
+    };
+}
+
+// the side of the cell `from` the neighbouring cell `to` is on, across the edge of a wrapping board
+// or through a portal too.
+fn side(from: IVec2, to: IVec2, board: &Board, portals: &Portals) -> Direction {
+    for direction in Direction::ALL {
+        if portals.next_cell(board, from, direction) == to {
+            return direction;
+        }
+    }
+    return Direction::Down;
+}
+
+// picks the piece of every snake node from its neighbours, the colored quads are shown until the atlas is loaded.
+// a step only changes the head, the node after it and the tail, so only those are looked at when the snake body
+// changed, unless the atlas has just been loaded. a turn of the head shows with the step that makes it.
+pub fn snake_sprite_system(
+    snake_atlas: Res<SnakeAtlas>,
+    images: Res<Assets<Image>>,
+    snake_body: Res<SnakeBody>,
+    board: Res<Board>,
+    portals: Res<Portals>,
+    mut was_loaded: Local<bool>,
+    mut retry: Local<bool>,
+    node_query: Query<(&SnakeNode, &Children)>,
+    mut quad_query: Query<&mut Visibility, (With<SnakeQuad>, Without<SnakePiece>)>,
+    mut piece_query: Query<(&mut TextureAtlasSprite, &mut Visibility), With<SnakePiece>>,
+) {
+    let loaded = images.get(&snake_atlas.image).is_some();
+    let full = loaded != *was_loaded;
+    *was_loaded = loaded;
+    if !full && !snake_body.is_changed() && !*retry {
+        return;
+    }
+    *retry = false;
+
+    let len = snake_body.len();
+    let indices: Vec<usize> = if full {
+        (0..len).collect()
+    } else {
+        [0, 1, len.saturating_sub(1)].into_iter().filter(|&i| i < len).collect()
+    };
+
+    for i in indices {
+        let (entity, cell) = snake_body.get(i).unwrap();
+        let Ok((node, children)) = node_query.get(entity) else {
+            // the head spawned while eating is only found a frame after the snake body got it.
+            *retry = true;
+            continue;
+        };
+        let index = match node.snake_type {
+            SnakeType::Head(direction) => head_index(direction),
+            SnakeType::Unknown => head_index(Direction::Down),
+            SnakeType::Body if i == 0 => body_index(Direction::Up, Direction::Down),
+            SnakeType::Body => {
+                let previous = side(cell, snake_body.get(i - 1).unwrap().1, &board, &portals);
+                match snake_body.get(i + 1) {
+                    Some((_, next)) => body_index(previous, side(cell, next, &board, &portals)),
+                    None => tail_index(previous),
+                }
+            }
+        };
+        for &child in children.iter() {
+            if let Ok(mut visibility) = quad_query.get_mut(child) {
+                let wanted = if loaded { Visibility::Hidden } else { Visibility::Inherited };
+                if *visibility != wanted {
+                    *visibility = wanted;
+                }
+            }
+            if let Ok((mut sprite, mut visibility)) = piece_query.get_mut(child) {
+                let wanted = if loaded { Visibility::Inherited } else { Visibility::Hidden };
+                if *visibility != wanted {
+                    *visibility = wanted;
+                }
+                if sprite.index != index {
+                    sprite.index = index;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// reads and writes the small ron files of the game, like the settings.
+
+// the files live in the config directory of the user.
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+
+    use bevy::log::warn;
+
+    fn file_path(name: &str) -> PathBuf {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(|| PathBuf::from("."));
+        return config_dir.join("snake").join(format!("{}.ron", name));
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        return std::fs::read_to_string(file_path(name)).ok();
+    }
+
+    pub fn write(name: &str, content: &str) {
+        let path = file_path(name);
+        if let Some(dir) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                warn!("failed to create {}: {}", dir.display(), err);
+                return;
+            }
+        }
+        if let Err(err) = std::fs::write(&path, content) {
+            warn!("failed to write {}: {}", path.display(), err);
+        }
+    }
+}
+
+// the browser has no file system, keep the files in the local storage.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use bevy::log::warn;
+
+    fn local_storage() -> Option<web_sys::Storage> {
+        return web_sys::window()?.local_storage().ok()?;
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        return local_storage()?.get_item(&format!("snake.{}", name)).ok()?;
+    }
+
+    pub fn write(name: &str, content: &str) {
+        let Some(storage) = local_storage() else {
+            warn!("the local storage is not available");
+            return;
+        };
+        if storage.set_item(&format!("snake.{}", name), content).is_err() {
+            warn!("failed to write the local storage");
+        }
+    }
+}
+
+pub use platform::{read, write};
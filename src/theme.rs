@@ -0,0 +1,239 @@
+use bevy::asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::reflect::TypePath;
+use bevy::utils::BoxedFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::data::component::ThemeColor;
+use crate::settings::{Settings, THEMES};
+
+// Every color and the font of the game, the built-in themes are the files in assets/themes.
+// a field missing in a file keeps the value of the classic theme.
+#[derive(Asset, TypePath, Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    pub font: String,
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub board: Color,
+    #[serde(with = "hex_color")]
+    pub board_border: Color,
+    #[serde(with = "hex_color")]
+    pub grid_even: Color,
+    #[serde(with = "hex_color")]
+    pub grid_odd: Color,
+    #[serde(with = "hex_color")]
+    pub snake_border: Color,
+    #[serde(with = "hex_color")]
+    pub snake_body: Color,
+    #[serde(with = "hex_color")]
+    pub food: Color,
+    #[serde(with = "hex_color")]
+    pub portal: Color,
+    #[serde(with = "hex_color")]
+    pub hazard: Color,
+    #[serde(with = "hex_color")]
+    pub score: Color,
+    #[serde(with = "hex_color")]
+    pub title: Color,
+    #[serde(with = "hex_color")]
+    pub game_over_title: Color,
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    #[serde(with = "hex_color")]
+    pub panel: Color,
+    #[serde(with = "hex_color")]
+    pub modal_panel: Color, // the panel of the settings, it hides the menu under it.
+    #[serde(with = "hex_color")]
+    pub backdrop: Color, // darkens the board under the pause menu.
+    #[serde(with = "hex_color")]
+    pub button: Color,
+    #[serde(with = "hex_color")]
+    pub button_hovered: Color,
+    #[serde(with = "hex_color")]
+    pub button_focused: Color,
+    #[serde(with = "hex_color")]
+    pub button_pressed: Color,
+    #[serde(with = "hex_color")]
+    pub touch_button: Color,
+}
+
+// the classic theme, it is used until the theme file is loaded.
+impl Default for Theme {
+    fn default() -> Self {
+        return Theme {
+            name: "Classic".to_string(),
+            font: "fonts/FiraSans-Bold.ttf".to_string(),
+            background: Color::hex("737376").unwrap(),
+            board: Color::rgba(0., 0.5, 0.25, 0.12),
+            board_border: Color::WHITE.with_a(0.5),
+            grid_even: Color::rgba(0.1, 0.24, 0.55, 0.3),
+            grid_odd: Color::rgba(0.2, 0.14, 0.95, 0.05),
+            snake_border: Color::rgba(0.7, 0.34, 0.85, 0.9),
+            snake_body: Color::rgb(0.25, 0.25, 0.75),
+            food: Color::rgba_u8(200, 40, 28, 255),
+            portal: Color::rgba_u8(0, 180, 216, 255),
+            hazard: Color::rgba_u8(240, 140, 0, 255),
+            score: Color::rgba_u8(119, 195, 200, 184),
+            title: Color::rgba_u8(147, 215, 197, 255),
+            game_over_title: Color::rgba_u8(117, 15, 127, 180),
+            text: Color::rgba_u8(136, 190, 200, 187),
+            panel: Color::rgba_u8(41, 147, 134, 200),
+            modal_panel: Color::rgba_u8(41, 147, 134, 255),
+            backdrop: Color::rgba(0., 0., 0., 0.45),
+            button: Color::rgb(0.15, 0.15, 0.15),
+            button_hovered: Color::rgb(0.25, 0.25, 0.25),
+            button_focused: Color::rgb(0.3, 0.45, 0.42),
+            button_pressed: Color::rgb(0.35, 0.65, 0.6),
+            touch_button: Color::rgba(0.15, 0.15, 0.15, 0.6),
+        };
+    }
+}
+
+impl Theme {
+    pub fn color(&self, slot: ThemeColor) -> Color {
+        return match slot {
+            ThemeColor::Board => self.board,
+            ThemeColor::BoardBorder => self.board_border,
+            ThemeColor::GridEven => self.grid_even,
+            ThemeColor::GridOdd => self.grid_odd,
+            ThemeColor::SnakeBorder => self.snake_border,
+            ThemeColor::SnakeBody => self.snake_body,
+            ThemeColor::Food => self.food,
+            ThemeColor::Portal => self.portal,
+            ThemeColor::Hazard => self.hazard,
+            ThemeColor::Score => self.score,
+            ThemeColor::Title => self.title,
+            ThemeColor::GameOverTitle => self.game_over_title,
+            ThemeColor::Text => self.text,
+            ThemeColor::Panel => self.panel,
+            ThemeColor::ModalPanel => self.modal_panel,
+            ThemeColor::Backdrop => self.backdrop,
+            ThemeColor::TouchButton => self.touch_button,
+        };
+    }
+}
+
+// the colors are written as "rrggbb" or "rrggbbaa" in the theme files.
+mod hex_color {
+    use bevy::prelude::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.as_rgba_u8();
+        return serializer.serialize_str(&format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a));
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        return Color::hex(&hex).map_err(|err| D::Error::custom(format!("{}: {:?}", hex, err)));
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+#[derive(Debug)]
+pub enum ThemeLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for ThemeLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            ThemeLoaderError::Io(err) => write!(f, "failed to read the theme: {}", err),
+            ThemeLoaderError::Ron(err) => write!(f, "failed to parse the theme: {}", err),
+        };
+    }
+}
+
+impl std::error::Error for ThemeLoaderError {}
+
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = ThemeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Theme, ThemeLoaderError>> {
+        return Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await.map_err(ThemeLoaderError::Io)?;
+            return ron::de::from_bytes::<Theme>(&bytes).map_err(ThemeLoaderError::Ron);
+        });
+    }
+
+    fn extensions(&self) -> &[&str] {
+        return &["theme.ron"];
+    }
+}
+
+// The handles of the built-in themes, in the order of THEMES.
+#[derive(Resource, Default)]
+pub struct ThemeHandles(pub Vec<Handle<Theme>>);
+
+pub fn load_themes_system(asset_server: Res<AssetServer>, mut handles: ResMut<ThemeHandles>) {
+    handles.0 = THEMES.iter().map(|(_, path)| asset_server.load(*path)).collect();
+}
+
+// makes the theme chosen in the settings the current one, as soon as its file is loaded.
+pub fn select_theme_system(
+    settings: Res<Settings>,
+    handles: Res<ThemeHandles>,
+    themes: Res<Assets<Theme>>,
+    mut events: EventReader<AssetEvent<Theme>>,
+    mut theme: ResMut<Theme>,
+) {
+    let loaded = events.read().count() > 0;
+    if !settings.is_changed() && !loaded {
+        return;
+    }
+    let index = THEMES.iter().position(|(name, _)| *name == settings.theme).unwrap_or(0);
+    let Some(selected) = handles.0.get(index).and_then(|handle| themes.get(handle)) else {
+        return;
+    };
+    if *theme != *selected {
+        *theme = selected.clone();
+    }
+}
+
+// paints the entities already on the window again when the theme changes.
+pub fn apply_theme_system(
+    theme: Res<Theme>,
+    asset_server: Res<AssetServer>,
+    mut clear_color: ResMut<ClearColor>,
+    mut query: Query<(
+        &ThemeColor,
+        Option<&mut BackgroundColor>,
+        Option<&mut Sprite>,
+        Option<&mut Text>,
+    )>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    clear_color.0 = theme.background;
+    let font: Handle<Font> = asset_server.load(&theme.font);
+    for (slot, background_color, sprite, text) in &mut query {
+        let color = theme.color(*slot);
+        if let Some(mut background_color) = background_color {
+            background_color.0 = color;
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.color = color;
+        }
+        if let Some(mut text) = text {
+            for section in &mut text.sections {
+                section.style.color = color;
+                section.style.font = font.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+
+use crate::constants::{SWIPE_MIN_DISTANCE, TOUCH_DPAD_BUTTON_SIZE};
+use crate::data::component::{ThemeColor, TouchDpad, TouchDpadButton};
+use crate::data::Direction;
+use crate::settings::Settings;
+use crate::theme::Theme;
+
+// What the touch screen asked for in this frame, it is read through ActionInput.
+#[derive(Resource, Default)]
+pub struct TouchState {
+    pub swipe: Option<Direction>,
+    pub dpad: Option<Direction>,
+    pub tap: bool,
+    // the finger followed for swipes and where it went down.
+    tracked: Option<(u64, Vec2)>,
+    swiped: bool,
+}
+
+impl TouchState {
+    pub fn direction(&self) -> Option<Direction> {
+        return self.swipe.or(self.dpad);
+    }
+}
+
+// turns the touches into swipes and taps, it runs after the ui found which button is pressed.
+pub fn touch_input_system(
+    touches: Res<Touches>,
+    mut touch_state: ResMut<TouchState>,
+    dpad_query: Query<(&Interaction, &TouchDpadButton)>,
+) {
+    touch_state.swipe = None;
+    touch_state.tap = false;
+    touch_state.dpad = dpad_query
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, button)| button.0);
+
+    // the touches on the D-pad are neither swipes nor taps.
+    if touch_state.dpad.is_none() {
+        if let Some(touch) = touches.iter_just_pressed().next() {
+            touch_state.tracked = Some((touch.id(), touch.position()));
+            touch_state.swiped = false;
+        }
+    }
+
+    let Some((id, start)) = touch_state.tracked else {
+        return;
+    };
+    // the swipe turns the snake as soon as the finger moved far enough, not when it is lifted.
+    if let Some(touch) = touches.get_pressed(id) {
+        let delta = touch.position() - start;
+        if !touch_state.swiped && delta.length() >= SWIPE_MIN_DISTANCE {
+            touch_state.swipe = Some(swipe_direction(delta));
+            touch_state.swiped = true;
+        }
+    }
+    if let Some(touch) = touches.iter_just_released().find(|touch| touch.id() == id) {
+        if !touch_state.swiped && (touch.position() - start).length() < SWIPE_MIN_DISTANCE {
+            touch_state.tap = true;
+        }
+        touch_state.tracked = None;
+    }
+    if touches.iter_just_canceled().any(|touch| touch.id() == id) {
+        touch_state.tracked = None;
+    }
+}
+
+// the window y axis points down.
+fn swipe_direction(delta: Vec2) -> Direction {
+    if delta.x.abs() > delta.y.abs() {
+        return if delta.x > 0. { Direction::Right } else { Direction::Left };
+    }
+    return if delta.y > 0. { Direction::Down } else { Direction::Up };
+}
+
+pub fn setup_touch_dpad(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    theme: Res<Theme>,
+) {
+    if !settings.touch_dpad {
+        return;
+    }
+    // a 3x3 grid in the bottom right corner, only the middle of every side has a button.
+    let cells = [
+        (None, Some(Direction::Up), None),
+        (Some(Direction::Left), None, Some(Direction::Right)),
+        (None, Some(Direction::Down), None),
+    ];
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(20.),
+                    bottom: Val::Px(20.),
+                    ..default()
+                },
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            TouchDpad,
+        ))
+        .with_children(|parent| {
+            for (left, middle, right) in cells {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for cell in [left, middle, right] {
+                            spawn_dpad_cell(parent, &asset_server, &theme, cell);
+                        }
+                    });
+            }
+        });
+}
+
+fn spawn_dpad_cell(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    theme: &Theme,
+    direction: Option<Direction>,
+) {
+    let style = Style {
+        width: Val::Px(TOUCH_DPAD_BUTTON_SIZE),
+        height: Val::Px(TOUCH_DPAD_BUTTON_SIZE),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let Some(direction) = direction else {
+        parent.spawn(NodeBundle { style, ..default() });
+        return;
+    };
+    let arrow = match direction {
+        Direction::Up => "^",
+        Direction::Down => "v",
+        Direction::Left => "<",
+        Direction::Right => ">",
+    };
+    parent
+        .spawn((
+            ButtonBundle {
+                style,
+                background_color: theme.touch_button.into(),
+                ..default()
+            },
+            TouchDpadButton(direction),
+            ThemeColor::TouchButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    arrow,
+                    TextStyle {
+                        font: asset_server.load(&theme.font),
+                        font_size: 40.0,
+                        color: theme.text,
+                    },
+                ),
+                ThemeColor::Text,
+            ));
+        });
+}
+
+pub fn dismiss_touch_dpad(mut commands: Commands, query: Query<Entity, With<TouchDpad>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -1,103 +1,91 @@
 use bevy::prelude::*;
 
-use crate::constants::{SNAKE_NODE_SIZE, BOUNDARY_WIDTH, BOUNDARY_HEIGHT};
-use crate::data::SnakeType;
+use crate::data::{
+    Board, DeathCause, FoodEatenEvent, FoodRules, GameOverEvent, HazardHitEvent, Portals, RoundMode, RunStats,
+    SelfCollisionPolicy, SnakeType, SoundEffect,
+};
 use crate::data::component::{SnakeNode, Food, SnakeLength};
-use crate::data::Direction;
+use crate::data::occupancy::{BoardOccupancy, CellContent, PLAYER_ONE};
+use crate::data::snake_body::SnakeBody;
 use crate::data::states::InGameState;
-use crate::game::{create_snake_node_bundle, create_food_bundle, create_snake_node_child_bundle};
+use crate::game::{spawn_food, spawn_snake_node};
+use crate::snake_sprite::SnakeAtlas;
+use crate::theme::Theme;
 
 
 pub fn should_eat_food(
     // mut world: &mut World,
     mut commands: Commands,
-    mut snake_query: Query<(&mut Transform, &mut SnakeNode)>,
-    food_query: Query<(Entity, &mut Transform), (With<Food>, Without<SnakeNode>)>,
+    mut snake_query: Query<&mut SnakeNode>,
+    food_query: Query<(Entity, &Food)>,
+    board: Res<Board>,
+    portals: Res<Portals>,
+    food_rules: Res<FoodRules>,
+    mut snake_body: ResMut<SnakeBody>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    mut run_stats: ResMut<RunStats>,
+    theme: Res<Theme>,
+    snake_atlas: Res<SnakeAtlas>,
+    mut sound_effects: EventWriter<SoundEffect>,
+    mut food_eaten_events: EventWriter<FoodEatenEvent>,
 ) {
-    if let Ok(food_trans) = food_query.get_single() {
-        let food_x = food_trans.1.translation.x;
-        let food_y = food_trans.1.translation.y;
-
-        let mut eat_food = false;
-        let mut head_dir = Direction::Up;
-        let mut all_snake_node_position: Vec<(f32, f32)> = Vec::new();
-        for mut snake_info in &mut snake_query {
-            all_snake_node_position.push((snake_info.0.translation.x, snake_info.0.translation.y));
-            if snake_info.1.snake_type == SnakeType::Body {
-                continue;
-            }
-            if food_x == snake_info.0.translation.x {
-                if let SnakeType::Head(dir) = snake_info.1.snake_type {
-                    if dir == Direction::Up
-                        && food_y == snake_info.0.translation.y + SNAKE_NODE_SIZE
-                    {
-                        eat_food = true;
-                    }
-                }
-                if let SnakeType::Head(dir) = snake_info.1.snake_type {
-                    if dir == Direction::Down
-                        && food_y == snake_info.0.translation.y - SNAKE_NODE_SIZE
-                    {
-                        eat_food = true;
-                    }
-                }
-            }
-            if food_y == snake_info.0.translation.y {
-                if let SnakeType::Head(dir) = snake_info.1.snake_type {
-                    if dir == Direction::Right
-                        && food_x == snake_info.0.translation.x + SNAKE_NODE_SIZE
-                    {
-                        eat_food = true;
-                    }
-                }
-                if let SnakeType::Head(dir) = snake_info.1.snake_type {
-                    if dir == Direction::Left
-                        && food_x == snake_info.0.translation.x - SNAKE_NODE_SIZE
-                    {
-                        eat_food = true;
-                    }
-                }
-            }
-            if eat_food {
-                if let SnakeType::Head(dir) = snake_info.1.snake_type {
-                    head_dir = dir;
-                    snake_info.1.snake_type = SnakeType::Body;
-                }
-            }
-            break;
-        }
-        if eat_food {
-            // dismiss the old food
-            commands.entity(food_trans.0).despawn();
-            // add food to the snake head
-            commands
-                .spawn((
-                    create_snake_node_bundle(food_x, food_y),
-                    SnakeNode {
-                        snake_type: SnakeType::Head(head_dir),
-                    },
-                ))
-                .with_children(|parent| {
-                    parent.spawn(create_snake_node_child_bundle());
-                });
-            all_snake_node_position.push((food_x, food_y));
-            // create new food object
-            commands.spawn((create_food_bundle(&all_snake_node_position), Food));
-        }
-    }
+    let Some((head, head_cell)) = snake_body.head() else {
+        return;
+    };
+    let Ok(mut head_node) = snake_query.get_mut(head) else {
+        return;
+    };
+    let SnakeType::Head(direction) = head_node.snake_type else {
+        return;
+    };
+    let food_cell = portals.next_cell(&board, head_cell, direction);
+    let CellContent::Food(_) = occupancy.get(food_cell) else {
+        return;
+    };
+    let Some((food, _)) = food_query.iter().find(|(_, food)| food.cell == food_cell) else {
+        return;
+    };
+
+    run_stats.food_eaten += 1;
+    sound_effects.send(SoundEffect::Eat);
+    // the food becomes the new head, the snake does not move in this step.
+    head_node.snake_type = SnakeType::Body;
+    commands.entity(food).despawn();
+    let position = Board::cell_position(food_cell);
+    let head = spawn_snake_node(
+        &mut commands,
+        position.x,
+        position.y,
+        SnakeType::Head(direction),
+        &theme,
+        &snake_atlas,
+    );
+    snake_body.grow(head, food_cell);
+    occupancy.add_segment(food_cell, PLAYER_ONE);
+    food_eaten_events.send(FoodEatenEvent { position, head });
+    // the eaten food is put back at once.
+    spawn_food(&mut commands, &mut occupancy, &theme, &food_rules);
 }
 
 pub fn snake_move(
-    mut query: Query<(&mut Transform, &SnakeNode)>,
+    mut commands: Commands,
+    mut query: Query<(&mut Transform, &mut SnakeNode)>,
     mut score_query: Query<(&mut Text, &mut SnakeLength)>,
     mut game_state: ResMut<NextState<InGameState>>,
-    cur_game_state: ResMut<State<InGameState>>,
+    board: Res<Board>,
+    portals: Res<Portals>,
+    mut snake_body: ResMut<SnakeBody>,
+    mut occupancy: ResMut<BoardOccupancy>,
+    round: Res<RoundMode>,
+    mut game_over_events: EventWriter<GameOverEvent>,
+    mut hazard_hits: EventWriter<HazardHitEvent>,
 ) {
-    if *cur_game_state.as_ref().get() == InGameState::GameOver {
+    // a second step in the frame of the crash would move the crashed snake on, see is_not_round_ending.
+    if matches!(game_state.0, Some(InGameState::Dying | InGameState::GameOver)) {
         return;
     }
 
-    let snake_length = query.iter().size_hint().0;
+    let snake_length = snake_body.len();
 
     // update the score player getted.
     let score_info = &mut score_query.single_mut();
@@ -108,85 +96,90 @@ pub fn snake_move(
         assert!(snake_length == len.0 + 1);
 
         // we have just eat the food, so do not move the snake, only update the socre.
-        let text = score_info.0.as_mut();
-        let style = text.sections.first().unwrap().style.clone();
-        text.sections = vec![TextSection::new(format!("Score: {}", len.0), style)];
+        show_score(score_info.0.as_mut(), len.0);
         return;
     }
 
-    let mut vec = Vec::from_iter(&mut query.iter_mut());
-    vec.reverse();
-
-    let head = (&mut vec)
-        .into_iter()
-        .find(|item| {
-            if let SnakeType::Head(_) = item.1.snake_type {
-                return true;
-            }
-            if SnakeType::Unknown == item.1.snake_type {
-                return true;
-            }
-            return false;
-        })
-        .unwrap();
+    let Some((head, head_cell)) = snake_body.head() else {
+        return;
+    };
+    let Ok((_, head_node)) = query.get(head) else {
+        return;
+    };
+    let SnakeType::Head(direction) = head_node.snake_type else {
+        return;
+    };
 
-    if SnakeType::Unknown == head.1.snake_type {
+    // only the tail moves, it becomes the head in front of the old one, or after the partner of a portal.
+    let next_cell = portals.next_cell(&board, head_cell, direction);
+    // the snake does not move into a hazard, see hazard_hit_system.
+    if occupancy.get(next_cell) == CellContent::Hazard {
+        hazard_hits.send(HazardHitEvent);
         return;
     }
-
-    let mut last_x: f32 = 0.;
-    let mut last_y: f32 = 0.;
-
-    // update snake head position
-    if let SnakeType::Head(direction) = head.1.snake_type {
-        last_x = head.0.translation.x;
-        last_y = head.0.translation.y;
-        match direction {
-            Direction::Down => head.0.translation.y -= SNAKE_NODE_SIZE,
-            Direction::Up => head.0.translation.y += SNAKE_NODE_SIZE,
-            Direction::Left => head.0.translation.x -= SNAKE_NODE_SIZE,
-            Direction::Right => head.0.translation.x += SNAKE_NODE_SIZE,
-        }
+    let (moved, tail_cell) = snake_body.step(next_cell);
+    // a snake passing through itself can have another segment on the cell the tail left.
+    occupancy.remove_segment(tail_cell);
+    let hit = occupancy.get(next_cell);
+    occupancy.add_segment(next_cell, PLAYER_ONE);
+    if let Ok((_, mut old_head)) = query.get_mut(head) {
+        old_head.snake_type = SnakeType::Body;
+    }
+    if let Ok((mut transform, mut new_head)) = query.get_mut(moved) {
+        let position = Board::cell_position(next_cell);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        new_head.snake_type = SnakeType::Head(direction);
     }
-    let head_translate_x = head.0.translation.x;
-    let head_translate_y = head.0.translation.y;
-
-    // update snake body position
-    for snake_node in &mut vec {
-        if SnakeType::Body == snake_node.1.snake_type {
-            let temp_x = snake_node.0.translation.x;
-            let temp_y = snake_node.0.translation.y;
-
-            snake_node.0.translation.x = last_x;
-            snake_node.0.translation.y = last_y;
 
-            last_x = temp_x;
-            last_y = temp_y;
-        }
+    let score = len.0;
+    let mut game_over = |cause: DeathCause| {
+        game_over_events.send(GameOverEvent {
+            cause,
+            score,
+            length: snake_length,
+        });
+        // the game over menu opens when the death animation is done, see dying.rs.
+        game_state.set(InGameState::Dying);
+    };
+
+    match hit {
+        // if the snake head hit the boundary, game over.
+        CellContent::Wall => game_over(DeathCause::Wall),
+        // if the snake head hit the snake body, it depends on the policy of the round.
+        CellContent::Snake(_) => match round.self_collision {
+            SelfCollisionPolicy::Die => game_over(DeathCause::Body),
+            SelfCollisionPolicy::CutAtImpact => {
+                let bitten = snake_body.iter().skip(1).position(|(_, cell)| cell == next_cell);
+                let bitten = bitten.map_or(0, |i| i + 1);
+                let (text, len) = (score_info.0.as_mut(), score_info.1.as_mut());
+                cut_from(&mut commands, &mut snake_body, &mut occupancy, (text, len), bitten);
+            }
+            SelfCollisionPolicy::PassThrough => {}
+        },
+        _ => {}
     }
+}
 
-    let boundary_translation_x =
-        SNAKE_NODE_SIZE * ((BOUNDARY_WIDTH / 2. / SNAKE_NODE_SIZE) as i32) as f32;
-    let boundary_translation_y =
-        SNAKE_NODE_SIZE * ((BOUNDARY_HEIGHT / 2. / SNAKE_NODE_SIZE) as i32) as f32;
+pub fn show_score(text: &mut Text, score: usize) {
+    let style = text.sections.first().unwrap().style.clone();
+    text.sections = vec![TextSection::new(format!("Score: {}", score), style)];
+}
 
-    // if the snake head hit the boundary, game over.
-    if head_translate_x.abs() > boundary_translation_x.abs()
-        || head_translate_y.abs() > boundary_translation_y.abs()
-    {
-        game_state.set(InGameState::GameOver);
-        return;
-    }
-    // if the snake head hit the snake body, game over.
-    for snake_node in &vec {
-        if SnakeType::Body == snake_node.1.snake_type {
-            let temp_x = snake_node.0.translation.x;
-            let temp_y = snake_node.0.translation.y;
-            if temp_x == head_translate_x && temp_y == head_translate_y {
-                info!("Failed !!!!");
-                game_state.set(InGameState::GameOver);
-                return;
-            }
-        }
+// takes the segments from the index to the tail off, the head is always kept. the score drops with the length.
+pub fn cut_from(
+    commands: &mut Commands,
+    snake_body: &mut SnakeBody,
+    occupancy: &mut BoardOccupancy,
+    score: (&mut Text, &mut SnakeLength),
+    index: usize,
+) {
+    // the head can be on the cell of the segment it bit, the cell stays taken by it.
+    for (entity, cell) in snake_body.split_off(index) {
+        commands.entity(entity).despawn_recursive();
+        occupancy.remove_segment(cell);
     }
-}
\ No newline at end of file
+    let (text, len) = score;
+    len.0 = snake_body.len() - 1;
+    show_score(text, len.0);
+}